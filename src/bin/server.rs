@@ -2,52 +2,190 @@ use axum::{routing::{post, get}, Router, Json};
 use axum::http::StatusCode;
 use serde::{Deserialize, Serialize};
 use std::net::SocketAddr;
+use std::time::Duration;
 use tokio::net::TcpListener;
-use flow_solver::{solve_board, SolverConfig, SolutionEdges};
+use flow_solver::{solve_board, Board, CancelToken, ColorPath, PruneStats, ReplacementPolicy, SolveStatus, SolverConfig, SolutionEdges, TableStats};
+use flow_solver::gen::{GenConfig, Generator};
+use rand::{rngs::StdRng, thread_rng, Rng, SeedableRng};
 use tower_http::services::ServeDir;
 
 #[derive(Debug, Deserialize)]
 struct SolveRequest {
     board: Vec<Vec<usize>>,
     #[serde(default)]
-    log_period: Option<usize>,
-    #[serde(default)]
     rotation: Option<usize>,
     #[serde(default)]
+    flip_horizontal: Option<bool>,
+    #[serde(default)]
+    flip_vertical: Option<bool>,
+    #[serde(default)]
     allow_zigzag: Option<bool>,
     #[serde(default)]
     use_table: Option<bool>,
     #[serde(default)]
+    table_size_mb: Option<usize>,
+    #[serde(default)]
+    table_policy: Option<ReplacementPolicy>,
+    #[serde(default)]
     use_vcut: Option<bool>,
     #[serde(default)]
     use_diagonals: Option<bool>,
+    #[serde(default)]
+    use_connectivity: Option<bool>,
+    #[serde(default)]
+    use_matching: Option<bool>,
+    #[serde(default)]
+    time_limit_ms: Option<u64>,
+    #[serde(default)]
+    max_nodes: Option<usize>,
+    #[serde(default)]
+    threads: Option<usize>,
+}
+
+/// Largest transposition table a request may ask for, in megabytes.
+const MAX_TABLE_MB: usize = 1024;
+
+/// Most threads a request may search on.
+const MAX_THREADS: usize = 16;
+
+/// Largest height and width a generated board may have.
+const MAX_GEN_SIZE: usize = 100;
+
+/// Time limit for requests that do not set `time_limit_ms`.
+const DEFAULT_TIME_LIMIT: Duration = Duration::from_secs(30);
+
+/// Cancels its token when dropped, e.g. when the client disconnects and the handler
+/// future is dropped before the solve finishes.
+struct CancelOnDrop(CancelToken);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.cancel();
+    }
 }
 
 #[derive(Debug, Serialize)]
 struct SolveResponse {
     solved: bool,
+    status: SolveStatus,
     nodes: usize,
     elapsed_ms: u128,
     edges: Option<SolutionEdges>,
     colors: Option<Vec<usize>>,
+    paths: Option<Vec<ColorPath>>,
+    table: Option<TableStats>,
+    pruned: PruneStats,
 }
 
 async fn solve_handler(Json(req): Json<SolveRequest>) -> Result<Json<SolveResponse>, (StatusCode, String)> {
+    if req.table_size_mb.is_some_and(|mb| !(1..=MAX_TABLE_MB).contains(&mb)) {
+        return Err((StatusCode::BAD_REQUEST, format!("table_size_mb must be between 1 and {}", MAX_TABLE_MB)));
+    }
+    if req.threads.is_some_and(|threads| !(1..=MAX_THREADS).contains(&threads)) {
+        return Err((StatusCode::BAD_REQUEST, format!("threads must be between 1 and {}", MAX_THREADS)));
+    }
+    let defaults = SolverConfig::default();
     let cfg = SolverConfig {
-        log_period: req.log_period,
         rotation: req.rotation.unwrap_or(0),
+        flip_horizontal: req.flip_horizontal.unwrap_or(false),
+        flip_vertical: req.flip_vertical.unwrap_or(false),
         allow_zigzag: req.allow_zigzag.unwrap_or(false),
         use_table: req.use_table.unwrap_or(false),
+        table_size_mb: req.table_size_mb.unwrap_or(defaults.table_size_mb),
+        table_policy: req.table_policy.unwrap_or(defaults.table_policy),
         use_vcut: req.use_vcut.unwrap_or(false),
         use_diagonals: req.use_diagonals.unwrap_or(true),
+        use_connectivity: req.use_connectivity.unwrap_or(false),
+        use_matching: req.use_matching.unwrap_or(false),
+        time_limit: Some(req.time_limit_ms.map_or(DEFAULT_TIME_LIMIT, Duration::from_millis)),
+        max_nodes: req.max_nodes,
+        cancel: Some(CancelToken::new()),
+        threads: req.threads.unwrap_or(defaults.threads),
     };
-    let res = solve_board(req.board, &cfg);
+    let board = Board::new(req.board)
+        .map_err(|err| (StatusCode::BAD_REQUEST, format!("Invalid board: {}", err)))?;
+    let _guard = CancelOnDrop(cfg.cancel.clone().unwrap());
+    let res = tokio::task::spawn_blocking(move || solve_board(&board, &cfg, &mut ()))
+        .await
+        .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, format!("Solver failed: {}", err)))?;
     Ok(Json(SolveResponse {
-        solved: res.solved,
+        solved: res.is_solved(),
+        status: res.status,
         nodes: res.nodes,
         elapsed_ms: res.elapsed.as_millis(),
-        edges: res.edges,
-        colors: res.colors,
+        edges: res.solution.as_ref().map(|solution| solution.edges().clone()),
+        colors: res.solution.as_ref().map(|solution| solution.colors().to_vec()),
+        paths: res.paths,
+        table: res.table,
+        pruned: res.pruned,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+struct GenRequest {
+    height: usize,
+    width: usize,
+    #[serde(default)]
+    seed: Option<u64>,
+    #[serde(default)]
+    allow_zigzag: Option<bool>,
+    #[serde(default)]
+    unique: Option<bool>,
+    #[serde(default)]
+    head_probability: Option<f64>,
+    #[serde(default)]
+    min_colors: Option<usize>,
+    #[serde(default)]
+    max_colors: Option<usize>,
+    #[serde(default)]
+    min_path_len: Option<usize>,
+    #[serde(default)]
+    max_path_len: Option<usize>,
+    #[serde(default)]
+    min_manhattan: Option<usize>,
+    #[serde(default)]
+    time_limit_ms: Option<u64>,
+}
+
+#[derive(Debug, Serialize)]
+struct GenResponse {
+    board: Board,
+    edges: SolutionEdges,
+    seed: u64,
+}
+
+async fn gen_handler(Json(req): Json<GenRequest>) -> Result<Json<GenResponse>, (StatusCode, String)> {
+    if !(1..=MAX_GEN_SIZE).contains(&req.height) || !(1..=MAX_GEN_SIZE).contains(&req.width) {
+        return Err((StatusCode::BAD_REQUEST, format!("height and width must be between 1 and {}", MAX_GEN_SIZE)));
+    }
+    let defaults = GenConfig::default();
+    let cfg = GenConfig {
+        height: req.height,
+        width: req.width,
+        head_probability: req.head_probability.unwrap_or(defaults.head_probability),
+        min_colors: req.min_colors.unwrap_or(defaults.min_colors),
+        max_colors: req.max_colors,
+        min_path_len: req.min_path_len.unwrap_or(defaults.min_path_len),
+        max_path_len: req.max_path_len,
+        min_manhattan: req.min_manhattan.unwrap_or(defaults.min_manhattan),
+        allow_zigzag: req.allow_zigzag.unwrap_or(false),
+        unique: req.unique.unwrap_or(false).then_some(32),
+        time_limit: Some(req.time_limit_ms.map_or(DEFAULT_TIME_LIMIT, Duration::from_millis)),
+        cancel: Some(CancelToken::new()),
+        ..defaults
+    };
+    let seed = req.seed.unwrap_or_else(|| thread_rng().gen());
+    let _guard = CancelOnDrop(cfg.cancel.clone().unwrap());
+    let mut generator = Generator::new(cfg, StdRng::seed_from_u64(seed))
+        .map_err(|err| (StatusCode::BAD_REQUEST, format!("Invalid options: {}", err)))?;
+    let generated = tokio::task::spawn_blocking(move || generator.generate())
+        .await
+        .map_err(|err| (StatusCode::INTERNAL_SERVER_ERROR, format!("Generator failed: {}", err)))?
+        .ok_or((StatusCode::UNPROCESSABLE_ENTITY, "No board satisfied the constraints in time".to_string()))?;
+    Ok(Json(GenResponse {
+        board: generated.board,
+        edges: generated.solution.edges().clone(),
+        seed,
     }))
 }
 
@@ -55,6 +193,7 @@ async fn solve_handler(Json(req): Json<SolveRequest>) -> Result<Json<SolveRespon
 async fn main() {
     let app = Router::new()
         .route("/solve", post(solve_handler))
+        .route("/gen", post(gen_handler))
         .route("/health", get(|| async { "ok" }))
         .nest_service("/", ServeDir::new("web"));
 
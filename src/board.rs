@@ -0,0 +1,344 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Puzzle;
+
+/// A rectangular puzzle whose colors each have exactly two endpoints. `0` is an
+/// empty cell and `k > 0` an endpoint of color `k`. Serializes as its rows.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<Vec<usize>>", into = "Vec<Vec<usize>>")]
+pub struct Board {
+    cells: Vec<Vec<usize>>,
+    endpoints: BTreeMap<usize, [(usize, usize); 2]>,
+}
+
+/// Text formats of a board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Whitespace-separated integers, `0` for an empty cell.
+    Numbers,
+    /// One character per cell, as in mzucker's solver: `.` for an empty cell and
+    /// `A`-`Z`, `a`-`z` for colors 1 to 52.
+    Letters,
+    /// A JSON [`Puzzle`] object with the board's rows and optional metadata.
+    Json,
+}
+
+const LETTERS: &[u8; 52] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+impl Format {
+    /// JSON if the input starts with `{`, letters if the rows have no whitespace inside
+    /// and use `.` or letters, numbers otherwise.
+    pub fn detect(input: &str) -> Format {
+        if input.trim_start().starts_with('{') {
+            return Format::Json;
+        }
+        let mut lines = cell_lines(input).map(|(_, text)| text.trim());
+        let letters = lines.clone().all(|text| !text.contains(char::is_whitespace))
+            && lines.any(|text| text.contains(|ch: char| ch == '.' || ch.is_ascii_alphabetic()));
+        return if letters { Format::Letters } else { Format::Numbers };
+    }
+}
+
+/// Lines holding cells, numbered from 1, without blank and `#` comment lines.
+fn cell_lines(input: &str) -> impl Iterator<Item = (usize, &str)> + Clone {
+    input.lines().enumerate()
+        .filter(|(_, text)| !text.trim().is_empty() && !text.trim_start().starts_with('#'))
+        .map(|(i, text)| (i + 1, text))
+}
+
+fn parse_numbers(line: usize, text: &str) -> Result<Vec<usize>, ParseError> {
+    let mut row = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(|ch: char| !ch.is_whitespace()) {
+        let token = rest[start..].split_whitespace().next().unwrap_or_default();
+        let column = text[..text.len() - rest.len() + start].chars().count() + 1;
+        match token.parse::<usize>() {
+            Ok(cell) => row.push(cell),
+            Err(_) => return Err(ParseError::InvalidToken { line, column, token: token.to_string() }),
+        }
+        rest = &rest[start + token.len()..];
+    }
+    return Ok(row);
+}
+
+fn parse_letters(line: usize, text: &str) -> Result<Vec<usize>, ParseError> {
+    let indent = text.chars().take_while(|ch| ch.is_whitespace()).count();
+    return text.trim().chars().enumerate()
+        .map(|(i, ch)| match ch {
+            '.' => Ok(0),
+            _ => LETTERS.iter().position(|&letter| letter as char == ch)
+                .map(|index| index + 1)
+                .ok_or_else(|| ParseError::InvalidToken { line, column: indent + i + 1, token: ch.to_string() }),
+        })
+        .collect();
+}
+
+/// Why a grid of cells is not a valid puzzle. Rows and columns are 0-based.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    /// The board has no cells.
+    Empty,
+    /// A row is longer or shorter than the first one.
+    RaggedRow { row: usize, expected: usize, found: usize },
+    /// A color does not have exactly two endpoints; `cells` lists where it appears.
+    UnpairedColor { color: usize, cells: Vec<(usize, usize)> },
+    /// The declared `(height, width)` differs from the board's.
+    Dimensions { expected: (usize, usize), found: (usize, usize) },
+    /// A known solution does not have one entry per cell of the board.
+    SolutionSize,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ValidationError::Empty => write!(f, "the board has no cells"),
+            ValidationError::RaggedRow { row, expected, found } => {
+                write!(f, "row {} has {} cells, expected {}", row + 1, found, expected)
+            }
+            ValidationError::UnpairedColor { color, cells } => {
+                let at: Vec<String> = cells.iter()
+                    .map(|(r, c)| format!("row {}, column {}", r + 1, c + 1))
+                    .collect();
+                write!(f, "color {} has {} endpoints, expected 2 (at {})", color, cells.len(), at.join("; "))
+            }
+            ValidationError::Dimensions { expected, found } => {
+                write!(f, "the board is {}x{}, but {}x{} was declared", found.0, found.1, expected.0, expected.1)
+            }
+            ValidationError::SolutionSize => write!(f, "the solution does not match the size of the board"),
+        }
+    }
+}
+
+impl Error for ValidationError {}
+
+/// Why a text puzzle could not be read. Lines and columns are 1-based positions in
+/// the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// A token is not a cell value of the format being read.
+    InvalidToken { line: usize, column: usize, token: String },
+    /// A line has a different number of cells than the first one.
+    RaggedRow { line: usize, expected: usize, found: usize },
+    /// A JSON puzzle is malformed or misses fields.
+    Json { line: usize, column: usize, message: String },
+    /// The grid parsed but is not a valid puzzle.
+    Invalid(ValidationError),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::InvalidToken { line, column, token } => {
+                write!(f, "line {}, column {}: `{}` is not a cell value", line, column, token)
+            }
+            ParseError::RaggedRow { line, expected, found } => {
+                write!(f, "line {}: {} cells, expected {}", line, found, expected)
+            }
+            ParseError::Json { message, .. } => write!(f, "{}", message),
+            ParseError::Invalid(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl Error for ParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ParseError::Invalid(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<ValidationError> for ParseError {
+    fn from(err: ValidationError) -> Self {
+        ParseError::Invalid(err)
+    }
+}
+
+impl Board {
+    /// Checks that `cells` is a non-empty rectangle and that every color has exactly
+    /// two endpoints.
+    pub fn new(cells: Vec<Vec<usize>>) -> Result<Board, ValidationError> {
+        let width = cells.first().map_or(0, |row| row.len());
+        if width == 0 {
+            return Err(ValidationError::Empty);
+        }
+        if let Some((row, found)) = cells.iter().map(|row| row.len()).enumerate().find(|&(_, len)| len != width) {
+            return Err(ValidationError::RaggedRow { row, expected: width, found });
+        }
+
+        let mut found: BTreeMap<usize, Vec<(usize, usize)>> = BTreeMap::new();
+        for (r, row) in cells.iter().enumerate() {
+            for (c, &color) in row.iter().enumerate() {
+                if color > 0 {
+                    found.entry(color).or_default().push((r, c));
+                }
+            }
+        }
+
+        let mut endpoints = BTreeMap::new();
+        for (color, ends) in found {
+            match ends[..] {
+                [a, b] => endpoints.insert(color, [a, b]),
+                _ => return Err(ValidationError::UnpairedColor { color, cells: ends }),
+            };
+        }
+
+        return Ok(Board { cells, endpoints });
+    }
+
+    /// Reads a board in the [`Format`] detected from `input`.
+    pub fn parse(input: &str) -> Result<Board, ParseError> {
+        Board::parse_as(input, Format::detect(input))
+    }
+
+    /// Reads a board in `format`, one row per line, skipping blank lines and `#` comment
+    /// lines.
+    pub fn parse_as(input: &str, format: Format) -> Result<Board, ParseError> {
+        if format == Format::Json {
+            return Ok(Puzzle::parse_as(input, format)?.board);
+        }
+
+        let mut cells: Vec<Vec<usize>> = Vec::new();
+        for (line, text) in cell_lines(input) {
+            let row = match format {
+                Format::Numbers => parse_numbers(line, text)?,
+                Format::Letters => parse_letters(line, text)?,
+                Format::Json => unreachable!(),
+            };
+
+            if let Some(first) = cells.first() {
+                if row.len() != first.len() {
+                    return Err(ParseError::RaggedRow { line, expected: first.len(), found: row.len() });
+                }
+            }
+            cells.push(row);
+        }
+
+        return Ok(Board::new(cells)?);
+    }
+
+    /// Writes the board in `format`, or returns `None` if some color has no letter.
+    pub fn to_text(&self, format: Format) -> Option<String> {
+        match format {
+            Format::Numbers => Some(self.to_string()),
+            Format::Json => Puzzle::new(self.clone()).to_text(format),
+            Format::Letters => {
+                let mut result = String::new();
+                for row in &self.cells {
+                    for &cell in row {
+                        result.push(match cell {
+                            0 => '.',
+                            _ => *LETTERS.get(cell - 1)? as char,
+                        });
+                    }
+                    result.push('\n');
+                }
+                Some(result)
+            }
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn width(&self) -> usize {
+        self.cells[0].len()
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> usize {
+        self.cells[row][col]
+    }
+
+    pub fn rows(&self) -> &[Vec<usize>] {
+        &self.cells
+    }
+
+    pub fn into_rows(self) -> Vec<Vec<usize>> {
+        self.cells
+    }
+
+    /// The two endpoints `(row, col)` of each color, in row-major order.
+    pub fn endpoints(&self) -> &BTreeMap<usize, [(usize, usize); 2]> {
+        &self.endpoints
+    }
+
+    pub fn num_colors(&self) -> usize {
+        self.endpoints.len()
+    }
+
+    /// Builds a `height x width` board whose cell `(r, c)` is this board's cell
+    /// `source(r, c)`.
+    fn remap(&self, height: usize, width: usize, source: impl Fn(usize, usize) -> (usize, usize)) -> Board {
+        let cells = (0..height)
+            .map(|r| (0..width).map(|c| {
+                let (sr, sc) = source(r, c);
+                self.cells[sr][sc]
+            }).collect())
+            .collect();
+        return Board::new(cells).expect("moving cells keeps a board valid");
+    }
+
+    /// The board turned a quarter counter-clockwise.
+    pub fn rotate_ccw(&self) -> Board {
+        let (h, w) = (self.height(), self.width());
+        return self.remap(w, h, |r, c| (c, w - 1 - r));
+    }
+
+    /// The board turned a quarter clockwise.
+    pub fn rotate_cw(&self) -> Board {
+        let (h, w) = (self.height(), self.width());
+        return self.remap(w, h, |r, c| (h - 1 - c, r));
+    }
+
+    /// The board mirrored left to right.
+    pub fn flip_horizontal(&self) -> Board {
+        let (h, w) = (self.height(), self.width());
+        return self.remap(h, w, |r, c| (r, w - 1 - c));
+    }
+
+    /// The board mirrored top to bottom.
+    pub fn flip_vertical(&self) -> Board {
+        let (h, w) = (self.height(), self.width());
+        return self.remap(h, w, |r, c| (h - 1 - r, c));
+    }
+}
+
+/// Writes the whitespace-separated integer format read by [`Board::parse`].
+impl fmt::Display for Board {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for row in &self.cells {
+            let cells: Vec<String> = row.iter().map(|cell| cell.to_string()).collect();
+            writeln!(f, "{}", cells.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Board {
+    type Err = ParseError;
+
+    fn from_str(input: &str) -> Result<Board, ParseError> {
+        Board::parse(input)
+    }
+}
+
+impl TryFrom<Vec<Vec<usize>>> for Board {
+    type Error = ValidationError;
+
+    fn try_from(cells: Vec<Vec<usize>>) -> Result<Board, ValidationError> {
+        Board::new(cells)
+    }
+}
+
+impl From<Board> for Vec<Vec<usize>> {
+    fn from(board: Board) -> Self {
+        board.cells
+    }
+}
@@ -1,10 +1,47 @@
+use std::error::Error;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+
 use crate::dsu::{ UnionFind, Unite };
+use crate::{ for_each_solution, Board, CancelToken, Solution, SolutionEdges, SolverConfig, ValidationError };
 
+/// Size and endpoints of a (partial) path. Placing a head unites the cell with a
+/// virtual node holding just that head, so it is undone like any other union.
 #[derive(Debug, Copy, Clone)]
-struct Dummy();
+struct Path {
+    cells: usize,
+    num_heads: usize,
+    heads: [usize; 2],
+}
+
+impl Unite for Path {
+    fn unite(self, oth: Self, _u: usize, _v: usize) -> Option<Self> {
+        if self.num_heads + oth.num_heads > 2 {
+            return None;
+        }
+        let mut heads = self.heads;
+        heads[self.num_heads..(self.num_heads + oth.num_heads)]
+            .copy_from_slice(&oth.heads[..oth.num_heads]);
+        Some(Path { cells: self.cells + oth.cells, num_heads: self.num_heads + oth.num_heads, heads })
+    }
+}
 
-impl Unite for Dummy {
-    fn unite(self, oth: Self, u: usize, v: usize) -> Option<Self> { Some(Dummy()) }
+/// Constraints on the paths of a [`GenFlow`], checked as soon as a path grows or closes.
+#[derive(Debug, Clone, Copy)]
+pub struct PathLimits {
+    pub min_len: usize,
+    pub max_len: Option<usize>,
+    /// Minimum Manhattan distance between the two heads of a path.
+    pub min_manhattan: usize,
+    pub max_paths: Option<usize>,
+}
+
+impl Default for PathLimits {
+    fn default() -> Self {
+        Self { min_len: 2, max_len: None, min_manhattan: 0, max_paths: None }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -15,16 +52,24 @@ pub struct GenFlow {
 
     down: Vec<bool>,
     right: Vec<bool>,
-    dsu: UnionFind<Dummy>,
+    dsu: UnionFind<Path>,
 
     search_order: Vec<usize>,
     search_depth: usize,
 
     allow_zigzag: bool,
+
+    limits: PathLimits,
+    paths: usize,
+    closed: Vec<bool>,
 }
 
 impl GenFlow {
     pub fn new(h: usize, w: usize, allow_zigzag: bool) -> Self {
+        Self::with_limits(h, w, allow_zigzag, PathLimits::default())
+    }
+
+    pub fn with_limits(h: usize, w: usize, allow_zigzag: bool, limits: PathLimits) -> Self {
         // let mut search_order: Vec<usize> = Vec::new();
         // for d in 0..(h + w - 1) {
         //     for r in 0..h {
@@ -41,12 +86,19 @@ impl GenFlow {
         return Self {
             h, w,
             heads: vec![false; h * w],
-            dsu: UnionFind::from(vec![Dummy(); h * w]),
+            dsu: UnionFind::from(
+                (0..(h * w)).map(|_| Path { cells: 1, num_heads: 0, heads: [0; 2] })
+                    .chain((0..(h * w)).map(|u| Path { cells: 0, num_heads: 1, heads: [u, 0] }))
+                    .collect()
+            ),
             down: vec![false; h * w],
             right: vec![false; h * w],
             search_order,
             search_depth: 0,
             allow_zigzag,
+            limits,
+            paths: 0,
+            closed: Vec::new(),
         };
     }
 
@@ -54,11 +106,32 @@ impl GenFlow {
 
     pub fn len(&self) -> usize { self.h * self.w }
 
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+
     pub fn solved(&self) -> bool { self.depth() == self.len() }
 
+    /// Number of paths with both heads placed.
+    pub fn paths(&self) -> usize { self.paths }
+
+    fn within_limits(&self, u: usize, changed: bool) -> bool {
+        let path = self.dsu.get_data(u);
+        let limits = &self.limits;
+        if limits.max_len.is_some_and(|max| path.cells > max) {
+            return false;
+        }
+        if !changed || path.num_heads < 2 {
+            return true;
+        }
+        let [a, b] = path.heads;
+        let manhattan = (a / self.w).abs_diff(b / self.w) + (a % self.w).abs_diff(b % self.w);
+        return path.cells >= limits.min_len &&
+            manhattan >= limits.min_manhattan &&
+            limits.max_paths.is_none_or(|max| self.paths < max);
+    }
+
     fn get_degree(&self, u: usize) -> usize {
         ((u >= self.w && self.down[u - self.w]) as usize) +
-        ((u % self.w > 0 && self.right[u - 1]) as usize)
+        ((!u.is_multiple_of(self.w) && self.right[u - 1]) as usize)
     }
 
     pub fn extend(&mut self, head: bool, down: bool, right: bool) -> bool {
@@ -67,7 +140,7 @@ impl GenFlow {
 
         if degree != 2 ||
            down && u + self.w >= self.len() ||
-           right && (u + 1) % self.w == 0 {
+           right && (u + 1).is_multiple_of(self.w) {
             return false;
         }
 
@@ -81,7 +154,7 @@ impl GenFlow {
                     return false;
                 }
             }
-            if down && u % self.w > 0 && self.right[u - 1] && self.down[u - 1] {
+            if down && !u.is_multiple_of(self.w) && self.right[u - 1] && self.down[u - 1] {
                 return false;
             }
         }
@@ -104,7 +177,23 @@ impl GenFlow {
             self.right[u] = true;
         }
 
+        if head {
+            let united = self.dsu.unite(u, self.len() + u);
+            debug_assert!(united, "a head cell is an open end of at most one head");
+        }
         self.heads[u] = head;
+
+        let changed = head || down || right;
+        if !self.within_limits(u, changed) {
+            self.search_depth += 1;
+            self.closed.push(false);
+            self.undo();
+            return false;
+        }
+
+        let closed = changed && self.dsu.get_data(u).num_heads == 2;
+        self.paths += closed as usize;
+        self.closed.push(closed);
         self.search_depth += 1;
 
         return true;
@@ -115,6 +204,14 @@ impl GenFlow {
 
         let u = self.search_order[self.depth()];
 
+        if self.closed.pop().unwrap() {
+            self.paths -= 1;
+        }
+
+        if self.heads[u] {
+            self.dsu.undo();
+        }
+
         if self.right[u] {
             self.dsu.undo();
             self.right[u] = false;
@@ -128,7 +225,9 @@ impl GenFlow {
         self.heads[u] = false;
     }
 
-    pub fn get_board(&self) -> Vec<Vec<usize>> {
+    /// The board of the heads placed so far, with colors numbered in row-major order.
+    /// Fails if some path is missing a head, so call it once `solved()`.
+    pub fn get_board(&self) -> Result<Board, ValidationError> {
         let mut heads: Vec<usize> = Vec::new();
         let mut board: Vec<Vec<usize>> = (0..self.h).map(|_| vec![0; self.w]).collect();
 
@@ -148,8 +247,12 @@ impl GenFlow {
                 }
             }
         }
-        
-        return board;
+
+        return Board::new(board);
+    }
+
+    pub fn edges(&self) -> SolutionEdges {
+        SolutionEdges { h: self.h, w: self.w, down: self.down.clone(), right: self.right.clone() }
     }
 
     pub fn dump(&self) -> String {
@@ -171,3 +274,327 @@ impl GenFlow {
         return result;
     }
 }
+
+/// Parameters of [`Generator`].
+#[derive(Debug, Clone)]
+pub struct GenConfig {
+    pub height: usize,
+    pub width: usize,
+    /// Chance that a visited cell becomes a path endpoint.
+    pub head_probability: f64,
+    /// Chance of drawing a down (respectively right) edge out of a visited cell.
+    pub edge_probability: f64,
+    /// Accepted number of colors, inclusive.
+    pub min_colors: usize,
+    pub max_colors: Option<usize>,
+    /// Accepted number of cells per path, inclusive.
+    pub min_path_len: usize,
+    pub max_path_len: Option<usize>,
+    /// Minimum Manhattan distance between the two heads of a color.
+    pub min_manhattan: usize,
+    pub allow_zigzag: bool,
+    /// Random moves tried at each cell before backtracking.
+    pub attempts_per_cell: usize,
+    /// Moves per cell after which a board that is still incomplete is restarted, as
+    /// random backtracking occasionally gets stuck for a very long time.
+    pub restart_after: usize,
+    /// Boards generated before giving up on the constraints above.
+    pub max_boards: usize,
+    /// If set, make the board uniquely solvable, splitting at most this many paths.
+    pub unique: Option<usize>,
+    /// Nodes each uniqueness check may search; a board whose check runs out is
+    /// regenerated.
+    pub unique_max_nodes: usize,
+    /// Give up once this much time has passed.
+    pub time_limit: Option<Duration>,
+    /// Give up once this token is cancelled.
+    pub cancel: Option<CancelToken>,
+}
+
+impl Default for GenConfig {
+    fn default() -> Self {
+        Self {
+            height: 10,
+            width: 10,
+            head_probability: 0.1,
+            edge_probability: 0.5,
+            min_colors: 1,
+            max_colors: None,
+            min_path_len: 2,
+            max_path_len: None,
+            min_manhattan: 0,
+            allow_zigzag: false,
+            attempts_per_cell: 16,
+            restart_after: 64,
+            max_boards: 1_000,
+            unique: None,
+            unique_max_nodes: 100_000,
+            time_limit: None,
+            cancel: None,
+        }
+    }
+}
+
+impl GenConfig {
+    /// Checks that boards of this configuration can exist at all.
+    pub fn validate(&self) -> Result<(), GenConfigError> {
+        if self.height == 0 || self.width == 0 {
+            return Err(GenConfigError::Empty);
+        }
+        for (name, value) in [("head_probability", self.head_probability), ("edge_probability", self.edge_probability)] {
+            if !(0.0..=1.0).contains(&value) {
+                return Err(GenConfigError::Probability { name, value });
+            }
+        }
+        if let Some(max) = self.max_colors.filter(|&max| max < self.min_colors) {
+            return Err(GenConfigError::Range { name: "colors", min: self.min_colors, max });
+        }
+        if let Some(max) = self.max_path_len.filter(|&max| max < self.min_path_len) {
+            return Err(GenConfigError::Range { name: "path_len", min: self.min_path_len, max });
+        }
+        let (cells, path_len) = (self.height * self.width, self.min_path_len.max(2));
+        if self.min_colors * path_len > cells {
+            return Err(GenConfigError::TooManyColors { colors: self.min_colors, path_len, cells });
+        }
+        return Ok(());
+    }
+}
+
+/// Why a [`GenConfig`] cannot generate any board.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GenConfigError {
+    /// The board has no cells.
+    Empty,
+    /// A probability lies outside `[0, 1]`.
+    Probability { name: &'static str, value: f64 },
+    /// A minimum exceeds its maximum.
+    Range { name: &'static str, min: usize, max: usize },
+    /// `colors` paths of at least `path_len` cells do not fit in `cells` cells.
+    TooManyColors { colors: usize, path_len: usize, cells: usize },
+}
+
+impl fmt::Display for GenConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GenConfigError::Empty => write!(f, "the board has no cells"),
+            GenConfigError::Probability { name, value } => {
+                write!(f, "{} must be between 0 and 1, got {}", name, value)
+            }
+            GenConfigError::Range { name, min, max } => {
+                write!(f, "min_{} is {}, more than max_{} {}", name, min, name, max)
+            }
+            GenConfigError::TooManyColors { colors, path_len, cells } => {
+                write!(f, "{} colors of at least {} cells do not fit in {} cells", colors, path_len, cells)
+            }
+        }
+    }
+}
+
+impl Error for GenConfigError {}
+
+/// A generated puzzle together with the paths it was built from.
+#[derive(Debug, Clone)]
+pub struct Generated {
+    pub board: Board,
+    pub solution: Solution,
+    /// Boards generated, including this one.
+    pub boards: usize,
+    /// Paths split to make the board unique.
+    pub repairs: usize,
+}
+
+pub struct Generator<R> {
+    cfg: GenConfig,
+    rng: R,
+    steps_left: usize,
+}
+
+impl<R: Rng> Generator<R> {
+    pub fn new(cfg: GenConfig, rng: R) -> Result<Self, GenConfigError> {
+        cfg.validate()?;
+        return Ok(Self { cfg, rng, steps_left: 0 });
+    }
+
+    fn fill(&mut self, state: &mut GenFlow) -> bool {
+        if state.solved() {
+            return state.paths() >= self.cfg.min_colors;
+        }
+
+        for _ in 0..self.cfg.attempts_per_cell {
+            if self.steps_left == 0 {
+                return false;
+            }
+            self.steps_left -= 1;
+
+            let head = self.rng.gen_bool(self.cfg.head_probability);
+            let down = self.rng.gen_bool(self.cfg.edge_probability);
+            let right = self.rng.gen_bool(self.cfg.edge_probability);
+            if state.extend(head, down, right) {
+                if self.fill(state) {
+                    return true;
+                }
+                state.undo();
+            }
+        }
+
+        return false;
+    }
+
+    fn accepts(&self, board: &Board, solution: &Solution) -> bool {
+        let lengths = path_lengths(solution);
+        let cfg = &self.cfg;
+        return lengths.len() >= cfg.min_colors &&
+            cfg.max_colors.is_none_or(|max| lengths.len() <= max) &&
+            lengths.iter().all(|&len| len >= cfg.min_path_len) &&
+            lengths.iter().all(|&len| cfg.max_path_len.is_none_or(|max| len <= max)) &&
+            head_distances(board).iter().all(|&dist| dist >= cfg.min_manhattan);
+    }
+
+    /// Generates boards until one satisfies the configuration, or returns `None` once
+    /// `max_boards` have been rejected, the time limit has passed or the token was
+    /// cancelled.
+    pub fn generate(&mut self) -> Option<Generated> {
+        let deadline = self.cfg.time_limit.map(|limit| Instant::now() + limit);
+        let limits = PathLimits {
+            min_len: self.cfg.min_path_len,
+            max_len: self.cfg.max_path_len,
+            min_manhattan: self.cfg.min_manhattan,
+            max_paths: self.cfg.max_colors,
+        };
+        let (h, w) = (self.cfg.height, self.cfg.width);
+
+        for boards in 1..=self.cfg.max_boards {
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) ||
+                self.cfg.cancel.as_ref().is_some_and(|cancel| cancel.is_cancelled()) {
+                return None;
+            }
+            let mut flow = GenFlow::with_limits(h, w, self.cfg.allow_zigzag, limits);
+            self.steps_left = self.cfg.restart_after * flow.len();
+            if !self.fill(&mut flow) {
+                continue;
+            }
+
+            let Ok(mut board) = flow.get_board() else {
+                continue;
+            };
+            let mut solution = Solution::from_edges(&board, flow.edges());
+            if !self.accepts(&board, &solution) {
+                continue;
+            }
+
+            let mut repairs = 0;
+            if let Some(max_repairs) = self.cfg.unique {
+                let solver_cfg = SolverConfig {
+                    max_nodes: Some(self.cfg.unique_max_nodes),
+                    time_limit: deadline.map(|deadline| deadline.saturating_duration_since(Instant::now())),
+                    cancel: self.cfg.cancel.clone(),
+                    ..SolverConfig::default()
+                };
+                match make_unique(&mut board, &mut solution, &solver_cfg, max_repairs) {
+                    Some(count) if self.accepts(&board, &solution) => repairs = count,
+                    _ => continue,
+                }
+            }
+
+            return Some(Generated { board, solution, boards, repairs });
+        }
+
+        return None;
+    }
+}
+
+/// Manhattan distance between the two heads of each color, in color order.
+pub fn head_distances(board: &Board) -> Vec<usize> {
+    return board.endpoints().values()
+        .map(|[a, b]| a.0.abs_diff(b.0) + a.1.abs_diff(b.1))
+        .collect();
+}
+
+/// Number of cells of each color's path, in color order.
+pub fn path_lengths(solution: &Solution) -> Vec<usize> {
+    return solution.paths().iter().map(|path| path.cells.len()).collect();
+}
+
+/// Follows the path of `edges` from `u`, never stepping back to `from`. Returns the
+/// number of cells visited and the cell where the path ends.
+fn walk(edges: &SolutionEdges, mut from: usize, mut u: usize) -> (usize, usize) {
+    let mut len = 1;
+    loop {
+        match edges.neighbors(u).into_iter().find(|&v| v != from) {
+            Some(v) => {
+                (from, u) = (u, v);
+                len += 1;
+            },
+            None => return (len, u),
+        }
+    }
+}
+
+/// Makes `board` uniquely solvable while keeping `known` a solution of it. Solutions
+/// are counted with `cfg`, but always with zigzags allowed, as a player may draw them,
+/// without the transposition table and with the vertical-cut and connectivity pruning,
+/// which keep counts unchanged. Whenever the solver finds a second solution, an edge of
+/// `known` that the other solution does not use is cut, splitting its path into two
+/// colors, which rules out that other solution. Cuts that leave both halves long are
+/// preferred. Returns the number of cuts, or `None` if `max_repairs` cuts did not
+/// suffice or a count hit the limits of `cfg` before finding a second solution.
+pub fn make_unique(
+    board: &mut Board,
+    known: &mut Solution,
+    cfg: &SolverConfig,
+    max_repairs: usize,
+) -> Option<usize> {
+    let cfg = &SolverConfig {
+        allow_zigzag: true,
+        use_table: false,
+        use_vcut: true,
+        use_connectivity: true,
+        ..cfg.clone()
+    };
+    let mut cells = board.rows().to_vec();
+    let mut edges = known.edges().clone();
+    let w = edges.w;
+    for repairs in 0..=max_repairs {
+        let current = Board::new(cells.clone()).expect("splitting a path keeps the board valid");
+        let mut other: Option<SolutionEdges> = None;
+        let result = for_each_solution(&current, cfg, Some(2), &mut (), |solution| {
+            if *solution.edges() != edges {
+                other.get_or_insert_with(|| solution.edges().clone());
+            }
+        });
+
+        let other = match other {
+            None if result.exhaustive && result.count == 1 => {
+                *known = Solution::from_edges(&current, edges);
+                *board = current;
+                return Some(repairs);
+            }
+            Some(other) if repairs < max_repairs => other,
+            _ => return None,
+        };
+
+        let is_head = |u: usize| cells[u / w][u % w] != 0;
+        let (u, v) = (0..edges.down.len())
+            .flat_map(|u| [
+                (edges.down[u] && !other.down[u]).then(|| (u, u + w)),
+                (edges.right[u] && !other.right[u]).then(|| (u, u + 1)),
+            ])
+            .flatten()
+            .filter(|&(u, v)| !is_head(u) && !is_head(v))
+            .max_by_key(|&(u, v)| walk(&edges, v, u).0.min(walk(&edges, u, v).0))?;
+
+        let color = cells.iter().flatten().max().unwrap() + 1;
+        let (_, end) = walk(&edges, u, v);
+        let start = walk(&edges, v, u).1;
+        cells[u / w][u % w] = cells[start / w][start % w];
+        cells[v / w][v % w] = color;
+        cells[end / w][end % w] = color;
+
+        if v == u + w {
+            edges.down[u] = false;
+        } else {
+            edges.right[u] = false;
+        }
+    }
+    return None;
+}
@@ -1,78 +1,245 @@
-use std::collections::HashSet;
+#![allow(clippy::needless_return)]
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 
 use serde::{Deserialize, Serialize};
 
 pub mod search;
+pub mod board;
 pub mod dsu;
 pub mod gen;
+pub mod matching;
+pub mod observer;
+mod parallel;
+pub mod portfolio;
+pub mod pack;
+pub mod puzzle;
+pub mod rate;
+pub mod render;
+pub mod solution;
+
+use crate::search::{SearchFlow, SearchConfig as InternalSearchConfig, TranspositionTable};
 
-use crate::search::{SearchFlow, SearchConfig as InternalSearchConfig};
+pub use crate::search::{PruneStats, ReplacementPolicy, TableStats};
+pub use crate::board::{Board, Format, ParseError, ValidationError};
+pub use crate::observer::{SearchObserver, StdoutLogger};
+pub use crate::portfolio::{portfolio_configs, solve_portfolio, PortfolioResult};
+pub use crate::pack::{parse_pack, write_pack, PackError};
+pub use crate::puzzle::Puzzle;
+pub use crate::solution::{ColorPath, Solution, SolutionEdges};
+pub use crate::rate::{rate_board, Difficulty, Rating};
 
 #[derive(Debug, Clone)]
 pub struct SolverConfig {
-    pub log_period: Option<usize>,
+    /// Quarter turns counter-clockwise applied to the board before solving, after any
+    /// flips. Solutions are still returned in the orientation of the input board.
     pub rotation: usize,
+    /// Mirror the board left to right before solving.
+    pub flip_horizontal: bool,
+    /// Mirror the board top to bottom before solving.
+    pub flip_vertical: bool,
     pub allow_zigzag: bool,
     pub use_table: bool,
+    /// Memory cap of the transposition table in megabytes.
+    pub table_size_mb: usize,
+    pub table_policy: ReplacementPolicy,
     pub use_vcut: bool,
     pub use_diagonals: bool,
+    pub use_connectivity: bool,
+    pub use_matching: bool,
+    /// Wall-clock time after which the search gives up.
+    pub time_limit: Option<Duration>,
+    /// Nodes after which the search gives up.
+    pub max_nodes: Option<usize>,
+    /// Stops the search once cancelled, e.g. from another thread.
+    pub cancel: Option<CancelToken>,
+    /// Threads that search subtrees in parallel; 1 searches on the calling thread.
+    pub threads: usize,
+}
+
+/// A shareable flag that stops the searches it is passed to.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+    /// Token whose cancellation also cancels this one.
+    parent: Option<Box<CancelToken>>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A token that is cancelled along with this one, but can also be cancelled on
+    /// its own without affecting this one.
+    pub fn child(&self) -> Self {
+        Self { cancelled: Arc::default(), parent: Some(Box::new(self.clone())) }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed) || self.parent.as_ref().is_some_and(|parent| parent.is_cancelled())
+    }
+}
+
+/// How a search ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SolveStatus {
+    Solved,
+    /// The whole search tree was explored without finding a solution.
+    Unsolvable,
+    TimedOut,
+    NodeLimit,
+    Cancelled,
+}
+
+impl fmt::Display for SolveStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            SolveStatus::Solved => "solved",
+            SolveStatus::Unsolvable => "unsolvable",
+            SolveStatus::TimedOut => "timed out",
+            SolveStatus::NodeLimit => "node limit",
+            SolveStatus::Cancelled => "cancelled",
+        })
+    }
 }
 
 impl Default for SolverConfig {
     fn default() -> Self {
         Self {
-            log_period: Some(1_000_000),
             rotation: 0,
+            flip_horizontal: false,
+            flip_vertical: false,
             allow_zigzag: false,
             use_table: false,
+            table_size_mb: 64,
+            table_policy: ReplacementPolicy::default(),
             use_vcut: false,
             use_diagonals: true,
+            use_connectivity: false,
+            use_matching: false,
+            time_limit: None,
+            max_nodes: None,
+            cancel: None,
+            threads: 1,
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SolutionEdges {
-    pub h: usize,
-    pub w: usize,
-    pub down: Vec<bool>,
-    pub right: Vec<bool>,
+/// Mutable bookkeeping shared by every node of one search.
+struct Search<'a> {
+    cfg: &'a SolverConfig,
+    nodes: usize,
+    solutions: usize,
+    table: Option<TranspositionTable>,
+    /// Called on every solved state; returning true stops the search.
+    on_solution: &'a mut dyn FnMut(&SearchFlow) -> bool,
+    observer: &'a mut dyn SearchObserver,
+    /// Most cells decided at once so far.
+    max_depth: usize,
+    deadline: Option<Instant>,
+    /// Set when this search is one worker of a parallel search.
+    shared: Option<&'a Shared>,
+    /// Nodes already added to `shared`.
+    reported: usize,
+    /// Why the search was cut short, if a limit or cancellation stopped it.
+    interrupted: Option<SolveStatus>,
 }
 
-fn ccw(board: &Vec<Vec<usize>>) -> Vec<Vec<usize>> {
-    (0..board[0].len())
-        .map(|row| {
-            (0..board.len())
-                .map(|col| board[col][board[0].len() - 1 - row])
-                .collect()
-        })
-        .collect()
-}
+impl<'a> Search<'a> {
+    fn new(
+        cfg: &'a SolverConfig,
+        on_solution: &'a mut dyn FnMut(&SearchFlow) -> bool,
+        observer: &'a mut dyn SearchObserver,
+    ) -> Self {
+        let table = if cfg.use_table {
+            Some(TranspositionTable::with_memory(cfg.table_size_mb, cfg.table_policy))
+        } else {
+            None
+        };
+        let deadline = cfg.time_limit.map(|limit| Instant::now() + limit);
+        Self { cfg, nodes: 0, solutions: 0, table, on_solution, observer, max_depth: 0, deadline, shared: None, reported: 0, interrupted: None }
+    }
 
-fn dfs_solve(
-    state: &mut SearchFlow,
-    num_nodes: &mut usize,
-    visited: &mut HashSet<Vec<usize>>,
-    cfg: &SolverConfig,
-) -> bool {
-    *num_nodes += 1;
+    /// Checks the node budget, and every 1024 nodes the deadline and cancellation.
+    fn interrupt(&mut self) -> bool {
+        if self.cfg.max_nodes.is_some_and(|max| self.nodes >= max) {
+            self.interrupted = Some(SolveStatus::NodeLimit);
+        } else if self.nodes.is_multiple_of(1024) {
+            if self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                self.interrupted = Some(SolveStatus::TimedOut);
+            } else if self.cfg.cancel.as_ref().is_some_and(|cancel| cancel.is_cancelled()) {
+                self.interrupted = Some(SolveStatus::Cancelled);
+            } else if let Some(shared) = self.shared {
+                let total = shared.add_nodes(self.nodes - self.reported);
+                self.reported = self.nodes;
+                if shared.solved.load(Ordering::Relaxed) {
+                    self.interrupted = Some(SolveStatus::Cancelled);
+                } else if self.cfg.max_nodes.is_some_and(|max| total >= max) {
+                    self.interrupted = Some(SolveStatus::NodeLimit);
+                }
+            }
+        }
+        return self.interrupted.is_some();
+    }
 
-    if cfg.use_table {
-        if visited.contains(&state.get_state()) {
-            return false;
+    /// Status of a search that `dfs_solve` returned `solved` from.
+    fn status(&self, solved: bool) -> SolveStatus {
+        match self.interrupted {
+            Some(status) => status,
+            None if solved => SolveStatus::Solved,
+            None => SolveStatus::Unsolvable,
         }
     }
+}
 
-    if let Some(period) = cfg.log_period {
-        if *num_nodes % period == 0 {
-            println!("Searched {} nodes", *num_nodes);
-            println!("{}\n", state.dump());
+/// State the workers of a parallel search share.
+struct Shared {
+    /// Nodes visited by all workers, added every 1024 nodes of each.
+    nodes: AtomicUsize,
+    /// Set once a worker finds a solution, stopping the others.
+    solved: AtomicBool,
+}
+
+impl Shared {
+    /// Adds `nodes` to the total and returns the new total.
+    fn add_nodes(&self, nodes: usize) -> usize {
+        return self.nodes.fetch_add(nodes, Ordering::Relaxed) + nodes;
+    }
+}
+
+/// Returns true once the search should stop: a solution was accepted by
+/// `on_solution`, or the search was interrupted.
+fn dfs_solve(state: &mut SearchFlow, search: &mut Search) -> bool {
+    if search.interrupt() {
+        return true;
+    }
+    search.nodes += 1;
+    let (start_nodes, start_solutions) = (search.nodes, search.solutions);
+    search.observer.node_visited(search.nodes, state);
+    if state.depth() > search.max_depth {
+        search.max_depth = state.depth();
+        search.observer.depth_reached(search.max_depth);
+    }
+
+    if let Some(table) = search.table.as_mut() {
+        if table.contains(state.hash()) {
+            return false;
         }
     }
 
     if state.solved() {
-        return true;
+        search.solutions += 1;
+        search.observer.solution_found(search.nodes, state);
+        return (search.on_solution)(state);
     }
 
     if !state.feasible() {
@@ -82,62 +249,167 @@ fn dfs_solve(
     for down in [false, true] {
         for right in [false, true] {
             if state.extend(down, right) {
-                if dfs_solve(state, num_nodes, visited, cfg) {
+                if dfs_solve(state, search) {
                     return true;
                 }
                 state.undo();
+                search.observer.backtrack(state.depth());
             }
         }
     }
 
-    if cfg.use_table {
-        visited.insert(state.get_state());
+    // only subtrees without any solution may be skipped when seen again
+    if search.solutions == start_solutions {
+        if let Some(table) = search.table.as_mut() {
+            table.insert(state.hash(), search.nodes - start_nodes);
+        }
     }
 
     false
 }
 
-#[derive(Debug, Clone)]
-pub struct SolveResult {
-    pub solved: bool,
-    pub edges: Option<SolutionEdges>,
-    pub nodes: usize,
-    pub elapsed: Duration,
-    pub colors: Option<Vec<usize>>,
-}
-
-pub fn solve_board(mut board: Vec<Vec<usize>>, cfg: &SolverConfig) -> SolveResult {
-    for _ in 0..cfg.rotation {
-        board = ccw(&board);
+/// The search state of `board` flipped and rotated as `cfg` asks.
+fn search_flow(board: &Board, cfg: &SolverConfig) -> SearchFlow {
+    let mut board = board.clone();
+    if cfg.flip_horizontal {
+        board = board.flip_horizontal();
+    }
+    if cfg.flip_vertical {
+        board = board.flip_vertical();
+    }
+    for _ in 0..cfg.rotation % 4 {
+        board = board.rotate_ccw();
     }
 
     let internal_cfg = InternalSearchConfig {
         allow_zigzag: cfg.allow_zigzag,
+        use_table: cfg.use_table,
         use_vcut: cfg.use_vcut,
         use_diagonals: cfg.use_diagonals,
+        use_connectivity: cfg.use_connectivity,
+        use_matching: cfg.use_matching,
     };
 
-    let mut solution = SearchFlow::from_with_config(&board, internal_cfg);
+    SearchFlow::from_with_config(board.rows(), internal_cfg)
+}
+
+/// Undoes the transforms of [`search_flow`] on a solution it found, returning it in the
+/// orientation of the input board.
+fn to_input_orientation(mut solution: Solution, cfg: &SolverConfig) -> Solution {
+    for _ in 0..cfg.rotation % 4 {
+        solution = solution.rotate_cw();
+    }
+    if cfg.flip_vertical {
+        solution = solution.flip_vertical();
+    }
+    if cfg.flip_horizontal {
+        solution = solution.flip_horizontal();
+    }
+    return solution;
+}
+
+#[derive(Debug, Clone)]
+pub struct SolveResult {
+    pub status: SolveStatus,
+    pub solution: Option<Solution>,
+    /// Each color's path from one endpoint to the other, in color order.
+    pub paths: Option<Vec<ColorPath>>,
+    pub nodes: usize,
+    pub elapsed: Duration,
+    pub table: Option<TableStats>,
+    pub pruned: PruneStats,
+}
+
+impl SolveResult {
+    pub fn is_solved(&self) -> bool {
+        self.status == SolveStatus::Solved
+    }
+}
+
+/// Searches `board` for a solution, reporting progress to `observer`. With more than
+/// one thread, subtrees near the root are searched in parallel and the first solution
+/// found stops the other threads.
+pub fn solve_board(board: &Board, cfg: &SolverConfig, observer: &mut dyn SearchObserver) -> SolveResult {
+    if cfg.threads > 1 {
+        return parallel::solve_board(board, cfg, observer);
+    }
+    let mut state = search_flow(board, cfg);
 
     let start_time = Instant::now();
-    let mut visited: HashSet<Vec<usize>> = HashSet::new();
-    let mut num_nodes: usize = 0;
+    let mut stop = |_: &SearchFlow| true;
+    let mut search = Search::new(cfg, &mut stop, observer);
 
-    let solved = dfs_solve(&mut solution, &mut num_nodes, &mut visited, cfg);
+    let solved = dfs_solve(&mut state, &mut search);
+    let status = search.status(solved);
+    let solution = (status == SolveStatus::Solved).then(|| to_input_orientation(state.solution(), cfg));
 
-    let (edges, colors) = if solved {
-        let (h, w, down, right) = solution.edges();
-        let colors = solution.colors();
-        (Some(SolutionEdges { h, w, down, right }), Some(colors))
-    } else {
-        (None, None)
+    SolveResult {
+        status,
+        paths: solution.as_ref().map(|solution| solution.paths()),
+        solution,
+        nodes: search.nodes,
+        elapsed: start_time.elapsed(),
+        table: search.table.map(|table| table.stats()),
+        pruned: state.prune_stats(),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CountResult {
+    /// Number of distinct solutions found, at most the requested limit.
+    pub count: usize,
+    /// Whether the whole search tree was explored, i.e. `count` is exact.
+    pub exhaustive: bool,
+    /// The limit or cancellation that cut the search short, if any.
+    pub interrupted: Option<SolveStatus>,
+    pub nodes: usize,
+    pub elapsed: Duration,
+    pub table: Option<TableStats>,
+    pub pruned: PruneStats,
+}
+
+/// Keeps backtracking past solved states and calls `visit` with each solution,
+/// stopping after `limit` solutions. With zigzags disallowed, only solutions free of
+/// zigzags are counted.
+pub fn for_each_solution<F>(
+    board: &Board,
+    cfg: &SolverConfig,
+    limit: Option<usize>,
+    observer: &mut dyn SearchObserver,
+    mut visit: F,
+) -> CountResult
+where
+    F: FnMut(Solution),
+{
+    let mut state = search_flow(board, cfg);
+
+    let start_time = Instant::now();
+    let mut count = 0;
+    let mut on_solution = |state: &SearchFlow| {
+        visit(to_input_orientation(state.solution(), cfg));
+        count += 1;
+        limit.is_some_and(|limit| count >= limit)
     };
+    let mut search = Search::new(cfg, &mut on_solution, observer);
 
-    SolveResult {
-        solved,
-        edges,
-        nodes: num_nodes,
+    let stopped = limit != Some(0) && dfs_solve(&mut state, &mut search);
+
+    CountResult {
+        count: search.solutions,
+        exhaustive: !stopped && limit != Some(0),
+        interrupted: search.interrupted,
+        nodes: search.nodes,
         elapsed: start_time.elapsed(),
-        colors,
+        table: search.table.map(|table| table.stats()),
+        pruned: state.prune_stats(),
     }
 }
+
+pub fn count_solutions(
+    board: &Board,
+    cfg: &SolverConfig,
+    limit: Option<usize>,
+    observer: &mut dyn SearchObserver,
+) -> CountResult {
+    for_each_solution(board, cfg, limit, observer, |_| {})
+}
@@ -1,88 +1,277 @@
-use std::{time::Instant, collections::HashSet};
+#![allow(clippy::needless_return)]
+
 use std::fs;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use std::io::IsTerminal;
 
-use rand::{ thread_rng, rngs::ThreadRng, Rng };
+use rand::{ thread_rng, rngs::StdRng, Rng, SeedableRng };
 
-use clap::{Parser, Subcommand, Args};
+use clap::{Parser, Subcommand, Args, ValueEnum};
 
-use flow_solver::gen::GenFlow;
+use flow_solver::gen::{GenConfig, Generator};
 use flow_solver::*;
 
 // modules are provided via the library crate
 
 #[derive(Debug, Clone)]
-pub struct ExtendedSolverConfig(SolverConfig, pub Option<String>);
+pub struct ExtendedSolverConfig {
+    pub solver: SolverConfig,
+    /// Where to also write the solution, if anywhere.
+    pub output: Option<String>,
+    /// Nodes between progress logs, or `None` to log nothing.
+    pub log_period: Option<usize>,
+}
 
 // dfs moved to library
 
-fn gen(state: &mut GenFlow, rng: &mut ThreadRng) -> bool {
-    if state.solved() {
-        return true;
+// rotation handled in library
+
+fn gen_entry(cfg: GenConfig, seed: Option<u64>, output_path: &str, format: Format, render: &RenderArgs) {
+    let seed = seed.unwrap_or_else(|| thread_rng().gen());
+    let mut generator = match Generator::new(cfg, StdRng::seed_from_u64(seed)) {
+        Ok(generator) => generator,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            std::process::exit(1);
+        }
+    };
+    let Some(generated) = generator.generate() else {
+        eprintln!("error: no board satisfied the constraints");
+        std::process::exit(1);
+    };
+
+    let puzzle = Puzzle {
+        seed: Some(seed),
+        solution: Some(generated.solution),
+        ..Puzzle::new(generated.board.clone())
+    };
+    let Some(output) = puzzle.to_text(format) else {
+        eprintln!("error: only colors 1 to 52 can be written as letters");
+        std::process::exit(1);
+    };
+    fs::write(output_path, &output)
+        .expect("Could not write file");
+    if let Some(path) = &render.svg {
+        fs::write(path, render::svg_board(&generated.board)).expect("Could not write SVG file");
+    }
+    if let Some(path) = &render.png {
+        let bytes = render::png_board(&generated.board, &render.png_style()).expect("Could not encode PNG");
+        fs::write(path, bytes).expect("Could not write PNG file");
+    }
+    if render.color.enabled() {
+        println!("# seed: {}", seed);
+        print!("{}", render::ansi_board(&generated.board));
+    } else {
+        print!("{}", output);
     }
+    println!("Boards generated: {}. Paths split: {}.", generated.boards, generated.repairs);
+}
 
-    for it in 0..16 {
-        if state.extend(rng.gen_bool(0.1), rng.gen_bool(0.5), rng.gen_bool(0.5)) {
-            if gen(state, rng) {
-                return true;
-            }
-            state.undo();
+/// Reads and validates a puzzle file in any format, exiting with a readable message if
+/// it is missing or malformed.
+fn read_puzzle(input_path: &str) -> Puzzle {
+    parse_puzzle(input_path, &read_input(input_path))
+}
+
+/// Reads a file, exiting with a readable message if it cannot be read.
+fn read_input(input_path: &str) -> String {
+    match fs::read_to_string(input_path) {
+        Ok(input) => input,
+        Err(err) => {
+            eprintln!("error: {}: {}", input_path, err);
+            std::process::exit(1);
         }
     }
+}
 
-    return false;
+/// Parses the contents of `input_path`, exiting with a readable message if they are
+/// malformed.
+fn parse_puzzle(input_path: &str, input: &str) -> Puzzle {
+    match Puzzle::parse(input) {
+        Ok(puzzle) => puzzle,
+        Err(err) => {
+            eprintln!("error: {}: {}", input_path, err);
+            std::process::exit(1);
+        }
+    }
 }
 
-// rotation handled in library
+fn read_board(input_path: &str) -> Board {
+    read_puzzle(input_path).board
+}
 
-fn gen_entry(h: usize, w: usize, allow_zigzag: bool, output_path: &str) {
-    let mut flow = GenFlow::new(h, w, allow_zigzag);
-    let mut rng = thread_rng();
-    while !gen(&mut flow, &mut rng) {}
-    let board = flow.get_board();
-    let mut output = String::new();
-    for row in board.iter() {
-        for cell in row.iter() {
-            output.push_str(&cell.to_string());
-            output.push(' ');
+fn solve_entry(
+    input_path: &str,
+    cfg: &ExtendedSolverConfig,
+    portfolio: Option<PortfolioMode>,
+    format: SolutionFormat,
+    render: &RenderArgs,
+) {
+    let puzzle = read_puzzle(input_path);
+
+    let result = match portfolio {
+        None => solve_board(&puzzle.board, &cfg.solver, &mut cfg.log_period.map(StdoutLogger::new)),
+        Some(mode) => {
+            let configs = portfolio_configs(
+                &cfg.solver,
+                matches!(mode, PortfolioMode::Symmetries),
+                matches!(mode, PortfolioMode::Heuristics),
+            );
+            let race = solve_portfolio(&puzzle.board, &configs);
+            match race.winner.map(|i| &configs[i]) {
+                Some(winner) => println!("Portfolio: rotation {}{} answered first (connectivity {}, matching {}), {} nodes in all",
+                    winner.rotation, if winner.flip_horizontal { " of the mirrored board" } else { "" },
+                    on_off(winner.use_connectivity), on_off(winner.use_matching), race.nodes),
+                None => println!("Portfolio: no configuration answered, {} nodes in all", race.nodes),
+            }
+            race.result
         }
-        output.push('\n');
+    };
+    if let Some(stats) = &result.table {
+        println!("Table: {} probes, {} hits ({:.1}%), {} stores, {} slots",
+            stats.probes, stats.hits, 100.0 * stats.hit_rate(), stats.stores, stats.capacity);
     }
-    fs::write(output_path, output)
-        .expect("Could not write file");
-    println!("{}", flow.dump());
-}
-
-fn solve_entry(input_path: &str, cfg: &ExtendedSolverConfig) {
-    let input = fs::read_to_string(input_path)
-        .expect("Could not read file");
-
-    let mut board: Vec<Vec<usize>> = input.lines().map(|line| {
-        line.split_whitespace().map(|cell| {
-            cell.parse::<usize>().expect("Misformatted file")
-        }).collect()
-    }).collect();
-
-    let result = solve_board(board, &cfg.0);
-    if result.solved {
-        let (h, w, down, right) = {
-            let e = result.edges.as_ref().unwrap();
-            (e.h, e.w, e.down.clone(), e.right.clone())
-        };
-        let mut out = String::new();
-        out.push_str(&format!("Solved: true\nNodes: {}\nElapsed: {:?}\n", result.nodes, result.elapsed));
-        out.push_str("Edges (down/right flattened) not printed here.\n");
-        if let Some(path) = &cfg.1 {
-            fs::write(path, out).expect("Could not write output file");
-        }
-        // Print ASCII dump using original mechanics via edges is non-trivial here; skip.
+    if cfg.solver.use_vcut || cfg.solver.use_connectivity || cfg.solver.use_matching {
+        println!("Pruned: {} by vcut, {} by connectivity, {} by matching",
+            result.pruned.vcut, result.pruned.connectivity, result.pruned.matching);
+    }
+    if let Some(solution) = &result.solution {
+        if render.color.enabled() {
+            print!("{}", render::ansi(solution));
+        } else {
+            print!("{}", render::text(solution));
+        }
+        if let Some(path) = &render.svg {
+            fs::write(path, render::svg(solution)).expect("Could not write SVG file");
+        }
+        if let Some(path) = &render.png {
+            let bytes = render::png(solution, &render.png_style()).expect("Could not encode PNG");
+            fs::write(path, bytes).expect("Could not write PNG file");
+        }
+        if let Some(path) = &cfg.output {
+            let json = match format {
+                SolutionFormat::Edges => serde_json::to_string(solution).expect("Could not serialize solution"),
+                SolutionFormat::Json => {
+                    let solved = Puzzle { solution: Some(solution.clone()), ..puzzle };
+                    solved.to_text(Format::Json).expect("JSON holds any board").trim_end().to_string()
+                }
+            };
+            fs::write(path, json + "\n").expect("Could not write output file");
+        }
         println!("Solved. Nodes: {}. Time: {:?}.", result.nodes, result.elapsed);
-    } else {
+    } else if result.status == SolveStatus::Unsolvable {
         println!("No solution found :(");
         println!("Searched {} nodes", result.nodes);
         println!("Time elapsed: {:?}", result.elapsed);
+    } else {
+        println!("Search stopped: {}", result.status);
+        println!("Searched {} nodes", result.nodes);
+        println!("Time elapsed: {:?}", result.elapsed);
     }
 }
 
+fn on_off(enabled: bool) -> &'static str {
+    if enabled { "on" } else { "off" }
+}
+
+fn count_entry(input_path: &str, cfg: &ExtendedSolverConfig, limit: Option<usize>) {
+    let board = read_board(input_path);
+
+    let result = count_solutions(&board, &cfg.solver, limit, &mut cfg.log_period.map(StdoutLogger::new));
+    if result.exhaustive {
+        println!("Solutions: {}", result.count);
+    } else if let Some(status) = result.interrupted {
+        println!("Solutions: at least {} (search stopped: {})", result.count, status);
+    } else {
+        println!("Solutions: at least {} (limit reached)", result.count);
+    }
+    println!("Searched {} nodes", result.nodes);
+    println!("Time elapsed: {:?}", result.elapsed);
+}
+
+fn rate_entry(input_paths: &[String]) {
+    let mut ratings: Vec<(&String, Rating)> = input_paths.iter()
+        .map(|path| (path, rate_board(&read_board(path))))
+        .collect();
+    ratings.sort_by(|a, b| a.1.difficulty.cmp(&b.1.difficulty).then(a.1.score.total_cmp(&b.1.score)));
+
+    for (path, rating) in ratings {
+        println!(
+            "{}: {:.1} ({}) nodes={} branching_depth={} forced={}/{} mean_path={:.1} max_path={}{}",
+            path, rating.score, rating.difficulty, rating.nodes, rating.max_branching_depth,
+            rating.forced_cells, rating.cells, rating.mean_path_len, rating.max_path_len,
+            if rating.solved { "" } else { " (unsolved)" },
+        );
+    }
+}
+
+/// Solves the puzzles of a pack on `jobs` threads, prints one line per puzzle and
+/// exits with status 1 if any is left unsolved.
+fn solve_pack_entry(pack_path: &str, cfg: &SolverConfig, jobs: usize) {
+    let puzzles = fs::read_to_string(pack_path)
+        .map_err(|err| err.to_string())
+        .and_then(|input| parse_pack(&input).map_err(|err| err.to_string()))
+        .unwrap_or_else(|err| {
+            eprintln!("error: {}: {}", pack_path, err);
+            std::process::exit(1);
+        });
+
+    let start_time = Instant::now();
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<Option<SolveResult>> = puzzles.iter().map(|_| None).collect();
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..jobs.max(1)).map(|_| scope.spawn(|| {
+            let mut solved = Vec::new();
+            loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(puzzle) = puzzles.get(i) else {
+                    return solved;
+                };
+                solved.push((i, solve_board(&puzzle.board, cfg, &mut ())));
+            }
+        })).collect();
+        for worker in workers {
+            for (i, result) in worker.join().expect("solver thread panicked") {
+                results[i] = Some(result);
+            }
+        }
+    });
+
+    println!("{:>4}  {:<24} {:>7}  {:<10} {:>12} {:>12}", "#", "title", "size", "result", "nodes", "time");
+    let (mut solved, mut nodes) = (0, 0);
+    for (i, (puzzle, result)) in puzzles.iter().zip(results.iter().flatten()).enumerate() {
+        let size = format!("{}x{}", puzzle.board.height(), puzzle.board.width());
+        println!("{:>4}  {:<24} {:>7}  {:<10} {:>12} {:>12}",
+            i + 1, puzzle.title.as_deref().unwrap_or("-"), size,
+            result.status.to_string(), result.nodes, format!("{:.1?}", result.elapsed));
+        solved += result.is_solved() as usize;
+        nodes += result.nodes;
+    }
+    println!("Solved {}/{} puzzles. Nodes: {}. Time: {:?}.", solved, puzzles.len(), nodes, start_time.elapsed());
+
+    if solved < puzzles.len() {
+        std::process::exit(1);
+    }
+}
+
+/// Rewrites a puzzle in another format, keeping its metadata.
+fn convert_entry(input_path: &str, output_path: &str, to: Option<Format>) {
+    let input = read_input(input_path);
+    let puzzle = parse_puzzle(input_path, &input);
+    let to = to.unwrap_or(match Format::detect(&input) {
+        Format::Numbers => Format::Letters,
+        Format::Letters | Format::Json => Format::Numbers,
+    });
+
+    let Some(text) = puzzle.to_text(to) else {
+        eprintln!("error: {}: only colors 1 to 52 can be written as letters", input_path);
+        std::process::exit(1);
+    };
+    fs::write(output_path, text).expect("Could not write file");
+}
+
 #[derive(Debug, Parser)]
 #[command(name = "flow_solver", version, about = "Flow puzzle generator and solver")]
 struct Cli {
@@ -96,36 +285,257 @@ enum Commands {
     Solve(SolveArgs),
     /// Generate a new puzzle to an output file
     Gen(GenArgs),
+    /// Solve every puzzle of a pack file and summarize the results
+    SolvePack(SolvePackArgs),
+    /// Rate the difficulty of puzzle files
+    Rate(RateArgs),
+    /// Convert a puzzle file between the number, letter and JSON formats
+    Convert(ConvertArgs),
+}
+
+#[derive(Debug, Args)]
+struct ConvertArgs {
+    /// Puzzle file to read, in either format
+    input_file: String,
+    /// Where to write the converted puzzle
+    output_file: String,
+    /// Format to write (default: the one the input is not in)
+    #[arg(long, value_enum)]
+    to: Option<TextFormat>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum TextFormat {
+    /// Whitespace-separated integers, 0 for an empty cell
+    Numbers,
+    /// One character per cell: '.' for an empty cell, A-Z and a-z for colors 1 to 52
+    Letters,
+    /// JSON object with the board, metadata and known solution
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum PortfolioMode {
+    /// All four rotations
+    Rotations,
+    /// All four rotations of the board and of its mirror image
+    Symmetries,
+    /// All four rotations, each with and without connectivity and matching pruning
+    Heuristics,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum SolutionFormat {
+    /// The solution's edges and cell colors
+    Edges,
+    /// The puzzle in the JSON format, with its metadata and the solution
+    Json,
+}
+
+impl From<TextFormat> for Format {
+    fn from(format: TextFormat) -> Self {
+        match format {
+            TextFormat::Numbers => Format::Numbers,
+            TextFormat::Letters => Format::Letters,
+            TextFormat::Json => Format::Json,
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+struct RateArgs {
+    /// Puzzle files to rate
+    #[arg(required = true)]
+    input_files: Vec<String>,
 }
 
 #[derive(Debug, Args)]
 struct SolveArgs {
     /// Input puzzle file path
     input_file: String,
-    /// Write solution/stats to this file
+    /// Count solutions instead of stopping at the first one
+    #[arg(long, default_value_t = false)]
+    count: bool,
+    /// Stop counting after N solutions
+    #[arg(long, value_name = "N", requires = "count")]
+    limit: Option<usize>,
+    /// Write the solution to this file
     #[arg(long, value_name = "PATH")]
     output: Option<String>,
+    /// What --output writes
+    #[arg(long, value_enum, default_value_t = SolutionFormat::Edges)]
+    format: SolutionFormat,
+    /// Race several configurations at once and keep the first answer
+    #[arg(long, value_enum, num_args = 0..=1, default_missing_value = "rotations", conflicts_with_all = ["count", "rotation", "flip_horizontal", "flip_vertical"])]
+    portfolio: Option<PortfolioMode>,
+    #[command(flatten)]
+    solver: SolverArgs,
+    #[command(flatten)]
+    log: LogArgs,
+    #[command(flatten)]
+    render: RenderArgs,
+}
+
+#[derive(Debug, Args)]
+struct SolvePackArgs {
+    /// Pack file: one JSON puzzle per line, or text puzzles separated by blank lines
+    pack_file: String,
+    /// Puzzles to solve at the same time
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    jobs: usize,
+    #[command(flatten)]
+    solver: SolverArgs,
+}
+
+#[derive(Debug, Args)]
+struct LogArgs {
     /// Log search progress every N nodes (omit for default; use --no-log to disable)
-    #[arg(long, value_name = "N")]
+    #[arg(long, value_name = "N", value_parser = parse_period)]
     log_period: Option<usize>,
     /// Disable logging entirely
     #[arg(long, default_value_t = false)]
     no_log: bool,
+}
+
+#[derive(Debug, Args)]
+struct SolverArgs {
     /// Rotate input counter-clockwise 0..=3 times
     #[arg(long, default_value_t = 0)]
     rotation: usize,
+    /// Mirror the input left to right before solving
+    #[arg(long, default_value_t = false)]
+    flip_horizontal: bool,
+    /// Mirror the input top to bottom before solving
+    #[arg(long, default_value_t = false)]
+    flip_vertical: bool,
     /// Allow local zigzags (default: off)
     #[arg(long, default_value_t = false)]
     allow_zigzag: bool,
-    /// Enable state dedup table (default: off; currently broken)
+    /// Enable transposition table of refuted states (default: off)
     #[arg(long, default_value_t = false)]
     use_table: bool,
+    /// Memory cap of the transposition table in megabytes
+    #[arg(long, value_name = "MB", default_value_t = 64)]
+    table_mb: usize,
+    /// Which entry to keep when two states share a table slot
+    #[arg(long, value_enum, default_value_t = TablePolicy::Always)]
+    table_policy: TablePolicy,
     /// Enable vertical cut heuristic (default: off)
     #[arg(long, default_value_t = false)]
     use_vcut: bool,
+    /// Enable reachability pruning of stranded colors and orphaned regions (default: off)
+    #[arg(long, default_value_t = false)]
+    use_connectivity: bool,
+    /// Enable pruning when frontier ends cannot all be matched to partners (default: off)
+    #[arg(long, default_value_t = false)]
+    use_matching: bool,
     /// Disable diagonal head-count pruning (default: on)
     #[arg(long, default_value_t = false)]
     no_diagonals: bool,
+    /// Give up after this many seconds
+    #[arg(long, value_name = "SECS", value_parser = parse_seconds)]
+    time_limit: Option<Duration>,
+    /// Give up after searching this many nodes
+    #[arg(long, value_name = "N")]
+    max_nodes: Option<usize>,
+    /// Search subtrees on this many threads
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    threads: usize,
+}
+
+#[derive(Debug, Args)]
+struct RenderArgs {
+    /// When to print the board in color
+    #[arg(long, value_enum, default_value_t = ColorMode::Auto)]
+    color: ColorMode,
+    /// Also draw the board to this SVG file
+    #[arg(long, value_name = "PATH")]
+    svg: Option<String>,
+    /// Also draw the board to this PNG file
+    #[arg(long, value_name = "PATH")]
+    png: Option<String>,
+    /// Cell size of the PNG image in pixels
+    #[arg(long, value_name = "PX", default_value_t = 44)]
+    png_cell: usize,
+    /// Comma-separated hex colors for the PNG image, e.g. "#e74c3c,#3498db"
+    #[arg(long, value_name = "COLORS", value_parser = parse_palette)]
+    palette: Option<Palette>,
+}
+
+impl RenderArgs {
+    fn png_style(&self) -> render::PngStyle {
+        let mut style = render::PngStyle { cell: self.png_cell, ..render::PngStyle::default() };
+        if let Some(Palette(palette)) = &self.palette {
+            style.palette = palette.clone();
+        }
+        return style;
+    }
+}
+
+fn parse_seconds(value: &str) -> Result<Duration, String> {
+    value.parse::<f64>()
+        .ok()
+        .and_then(|secs| Duration::try_from_secs_f64(secs).ok())
+        .ok_or_else(|| format!("invalid duration `{}`, expected seconds", value))
+}
+
+fn parse_period(value: &str) -> Result<usize, String> {
+    value.parse::<usize>()
+        .ok()
+        .filter(|&period| period > 0)
+        .ok_or_else(|| format!("invalid period `{}`, expected a positive number of nodes", value))
+}
+
+#[derive(Debug, Clone)]
+struct Palette(Vec<(u8, u8, u8)>);
+
+fn parse_palette(value: &str) -> Result<Palette, String> {
+    value.split(',')
+        .map(|hex| {
+            let hex = hex.trim().trim_start_matches('#');
+            let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2).unwrap_or(""), 16);
+            match (hex.len(), channel(0), channel(2), channel(4)) {
+                (6, Ok(r), Ok(g), Ok(b)) => Ok((r, g, b)),
+                _ => Err(format!("invalid color `{}`, expected #rrggbb", hex)),
+            }
+        })
+        .collect::<Result<_, _>>()
+        .map(Palette)
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ColorMode {
+    /// Color when printing to a terminal and NO_COLOR is unset
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorMode {
+    fn enabled(self) -> bool {
+        match self {
+            ColorMode::Auto => std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+            ColorMode::Always => true,
+            ColorMode::Never => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum TablePolicy {
+    /// Always overwrite with the newest state
+    Always,
+    /// Keep the state whose refutation took more nodes
+    PreferLarger,
+}
+
+impl From<TablePolicy> for ReplacementPolicy {
+    fn from(policy: TablePolicy) -> Self {
+        match policy {
+            TablePolicy::Always => ReplacementPolicy::Always,
+            TablePolicy::PreferLarger => ReplacementPolicy::PreferLarger,
+        }
+    }
 }
 
 #[derive(Debug, Args)]
@@ -139,24 +549,101 @@ struct GenArgs {
     /// Allow local zigzags while generating (default: off)
     #[arg(long, default_value_t = false)]
     allow_zigzag: bool,
+    /// Split paths or regenerate until the puzzle has exactly one solution
+    #[arg(long, default_value_t = false)]
+    unique: bool,
+    /// With --unique, path splits to try before regenerating the board
+    #[arg(long, value_name = "N", default_value_t = 32, requires = "unique")]
+    max_repairs: usize,
+    /// With --unique, nodes each uniqueness check may search before the board is regenerated
+    #[arg(long, value_name = "N", default_value_t = 100_000, requires = "unique")]
+    unique_max_nodes: usize,
+    /// Seed for the random generator; the same seed and size give the same board
+    #[arg(long, value_name = "SEED")]
+    seed: Option<u64>,
+    /// Chance that a cell becomes a path endpoint
+    #[arg(long, value_name = "P", default_value_t = 0.1)]
+    head_probability: f64,
+    /// Minimum number of colors
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    min_colors: usize,
+    /// Maximum number of colors
+    #[arg(long, value_name = "N")]
+    max_colors: Option<usize>,
+    /// Exact number of colors (sets both --min-colors and --max-colors)
+    #[arg(long, value_name = "N", conflicts_with_all = ["min_colors", "max_colors"])]
+    colors: Option<usize>,
+    /// Minimum number of cells per path
+    #[arg(long, value_name = "N", default_value_t = 2)]
+    min_path_len: usize,
+    /// Maximum number of cells per path
+    #[arg(long, value_name = "N")]
+    max_path_len: Option<usize>,
+    /// Minimum Manhattan distance between the two endpoints of a color
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    min_manhattan: usize,
+    /// Boards to generate before giving up on the constraints
+    #[arg(long, value_name = "N", default_value_t = 1_000)]
+    max_boards: usize,
+    /// Format of the output file
+    #[arg(long, value_enum, default_value_t = TextFormat::Numbers)]
+    format: TextFormat,
+    #[command(flatten)]
+    render: RenderArgs,
+}
+
+fn build_gen_config(args: &GenArgs) -> GenConfig {
+    GenConfig {
+        height: args.height,
+        width: args.width,
+        head_probability: args.head_probability,
+        min_colors: args.colors.unwrap_or(args.min_colors),
+        max_colors: args.colors.or(args.max_colors),
+        min_path_len: args.min_path_len,
+        max_path_len: args.max_path_len,
+        min_manhattan: args.min_manhattan,
+        allow_zigzag: args.allow_zigzag,
+        max_boards: args.max_boards,
+        unique: args.unique.then_some(args.max_repairs),
+        unique_max_nodes: args.unique_max_nodes,
+        ..GenConfig::default()
+    }
 }
 
 fn build_solver_config(args: &SolveArgs) -> ExtendedSolverConfig {
-    let mut cfg = SolverConfig::default();
-    cfg.rotation = args.rotation;
-    cfg.allow_zigzag = args.allow_zigzag;
-    cfg.use_table = args.use_table;
-    cfg.use_vcut = args.use_vcut;
-    cfg.use_diagonals = !args.no_diagonals;
-    cfg.log_period = if args.no_log {
+    ExtendedSolverConfig {
+        solver: solver_config(&args.solver),
+        output: args.output.clone(),
+        log_period: log_period(&args.log),
+    }
+}
+
+fn solver_config(args: &SolverArgs) -> SolverConfig {
+    SolverConfig {
+        rotation: args.rotation,
+        flip_horizontal: args.flip_horizontal,
+        flip_vertical: args.flip_vertical,
+        allow_zigzag: args.allow_zigzag,
+        use_table: args.use_table,
+        table_size_mb: args.table_mb,
+        table_policy: args.table_policy.into(),
+        use_vcut: args.use_vcut,
+        use_diagonals: !args.no_diagonals,
+        use_connectivity: args.use_connectivity,
+        use_matching: args.use_matching,
+        time_limit: args.time_limit,
+        max_nodes: args.max_nodes,
+        threads: args.threads,
+        ..SolverConfig::default()
+    }
+}
+
+fn log_period(args: &LogArgs) -> Option<usize> {
+    if args.no_log {
         None
     } else {
-        match args.log_period {
-            Some(n) => Some(n),
-            None => cfg.log_period, // keep default Some(1_000_000)
-        }
-    };
-    ExtendedSolverConfig(cfg, args.output.clone())
+        Some(args.log_period.unwrap_or(1_000_000))
+    }
 }
 
 fn main() {
@@ -164,10 +651,23 @@ fn main() {
     match cli.command {
         Commands::Solve(args) => {
             let cfg = build_solver_config(&args);
-            solve_entry(&args.input_file, &cfg);
+            if args.count {
+                count_entry(&args.input_file, &cfg, args.limit);
+            } else {
+                solve_entry(&args.input_file, &cfg, args.portfolio, args.format, &args.render);
+            }
+        }
+        Commands::SolvePack(args) => {
+            solve_pack_entry(&args.pack_file, &solver_config(&args.solver), args.jobs);
+        }
+        Commands::Rate(args) => {
+            rate_entry(&args.input_files);
+        }
+        Commands::Convert(args) => {
+            convert_entry(&args.input_file, &args.output_file, args.to.map(Format::from));
         }
         Commands::Gen(args) => {
-            gen_entry(args.height, args.width, args.allow_zigzag, &args.output_file);
+            gen_entry(build_gen_config(&args), args.seed, &args.output_file, args.format.into(), &args.render);
         }
     }
 }
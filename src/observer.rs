@@ -0,0 +1,116 @@
+use crate::search::SearchFlow;
+
+/// Receives progress events from a search. Every hook does nothing by default, so an
+/// observer only implements the events it needs. `()` observes nothing.
+///
+/// A parallel search passes the events of all its threads to the observer one at a
+/// time; node counts and depths are then those of the thread that sent the event.
+pub trait SearchObserver: Send {
+    /// Called on every node, with the number of nodes visited so far.
+    fn node_visited(&mut self, _nodes: usize, _state: &SearchFlow) {}
+
+    /// Called when the search first decides more cells than ever before.
+    fn depth_reached(&mut self, _depth: usize) {}
+
+    /// Called when the search undoes a move, returning to `depth` decided cells.
+    fn backtrack(&mut self, _depth: usize) {}
+
+    /// Called on every solved state, with the number of nodes visited so far.
+    fn solution_found(&mut self, _nodes: usize, _state: &SearchFlow) {}
+
+    /// Whether the observer wants any events at all. A parallel search does not pass
+    /// events between threads to an observer that returns false.
+    fn is_active(&self) -> bool {
+        return true;
+    }
+
+    /// Only node counts that are multiples of this are of interest to `node_visited`.
+    /// A parallel search passes just those nodes between threads.
+    fn node_period(&self) -> usize {
+        return 1;
+    }
+
+    /// Whether the observer wants `backtrack`. A parallel search does not pass
+    /// backtracks between threads to an observer that returns false.
+    fn observes_backtracks(&self) -> bool {
+        return true;
+    }
+}
+
+impl SearchObserver for () {
+    fn is_active(&self) -> bool {
+        return false;
+    }
+}
+
+impl<O: SearchObserver> SearchObserver for Option<O> {
+    fn node_visited(&mut self, nodes: usize, state: &SearchFlow) {
+        if let Some(observer) = self {
+            observer.node_visited(nodes, state);
+        }
+    }
+
+    fn depth_reached(&mut self, depth: usize) {
+        if let Some(observer) = self {
+            observer.depth_reached(depth);
+        }
+    }
+
+    fn backtrack(&mut self, depth: usize) {
+        if let Some(observer) = self {
+            observer.backtrack(depth);
+        }
+    }
+
+    fn solution_found(&mut self, nodes: usize, state: &SearchFlow) {
+        if let Some(observer) = self {
+            observer.solution_found(nodes, state);
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        return self.as_ref().is_some_and(|observer| observer.is_active());
+    }
+
+    fn node_period(&self) -> usize {
+        return self.as_ref().map_or(1, |observer| observer.node_period());
+    }
+
+    fn observes_backtracks(&self) -> bool {
+        return self.as_ref().is_some_and(|observer| observer.observes_backtracks());
+    }
+}
+
+/// Prints the node count and the search state to stdout every `period` nodes. A
+/// period of 0 prints nothing.
+#[derive(Debug, Clone)]
+pub struct StdoutLogger {
+    pub period: usize,
+}
+
+impl StdoutLogger {
+    pub fn new(period: usize) -> Self {
+        Self { period }
+    }
+}
+
+impl SearchObserver for StdoutLogger {
+    fn node_visited(&mut self, nodes: usize, state: &SearchFlow) {
+        if self.period > 0 && nodes.is_multiple_of(self.period) {
+            println!("Searched {} nodes", nodes);
+            println!("{}\n", state.dump());
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        return self.period > 0;
+    }
+
+    fn node_period(&self) -> usize {
+        return self.period.max(1);
+    }
+
+    fn observes_backtracks(&self) -> bool {
+        return false;
+    }
+}
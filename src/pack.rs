@@ -0,0 +1,64 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::{Format, ParseError, Puzzle};
+
+/// Why one entry of a pack could not be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackError {
+    /// Position of the entry in the pack, from 1.
+    pub entry: usize,
+    /// Line of the pack where the entry starts, from 1.
+    pub line: usize,
+    pub error: ParseError,
+}
+
+impl fmt::Display for PackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "puzzle {} (starting at line {}): {}", self.entry, self.line, self.error)
+    }
+}
+
+impl Error for PackError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// Reads a pack of puzzles: either one JSON puzzle per line, or text puzzles separated
+/// by blank lines, each with its own `# key: value` metadata lines.
+pub fn parse_pack(input: &str) -> Result<Vec<Puzzle>, PackError> {
+    let json = input.trim_start().starts_with('{');
+
+    let mut entries: Vec<(usize, String)> = Vec::new();
+    let mut block: Option<(usize, String)> = None;
+    for (i, line) in input.lines().enumerate() {
+        if line.trim().is_empty() || json {
+            entries.extend(block.take());
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (_, text) = block.get_or_insert_with(|| (i + 1, String::new()));
+        text.push_str(line);
+        text.push('\n');
+    }
+    entries.extend(block);
+
+    return entries.into_iter().enumerate()
+        .map(|(i, (line, text))| {
+            let format = if json { Format::Json } else { Format::detect(&text) };
+            Puzzle::parse_as(&text, format).map_err(|error| PackError { entry: i + 1, line, error })
+        })
+        .collect();
+}
+
+/// Writes `puzzles` as a pack: one per line for JSON, separated by blank lines
+/// otherwise. Returns `None` if some board cannot be written as letters.
+pub fn write_pack(puzzles: &[Puzzle], format: Format) -> Option<String> {
+    let texts = puzzles.iter()
+        .map(|puzzle| puzzle.to_text(format))
+        .collect::<Option<Vec<String>>>()?;
+    let separator = if format == Format::Json { "" } else { "\n" };
+    return Some(texts.join(separator));
+}
@@ -0,0 +1,212 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Instant;
+
+use crate::search::SearchFlow;
+use crate::{
+    dfs_solve, search_flow, to_input_orientation, Board, PruneStats, SearchObserver, Search, Shared, Solution,
+    SolveResult, SolveStatus, SolverConfig, TableStats,
+};
+
+/// Subtrees to split off per thread, so that threads done early can pick up more.
+const SUBTREES_PER_THREAD: usize = 16;
+
+/// Forwards the events of one thread to the observer of the whole search, locking it
+/// only for the nodes and backtracks it asked for.
+struct Forward<'a, 'b> {
+    observer: &'a Mutex<&'b mut dyn SearchObserver>,
+    node_period: usize,
+    backtracks: bool,
+}
+
+impl<'a, 'b> Forward<'a, 'b> {
+    fn new(observer: &'a Mutex<&'b mut dyn SearchObserver>) -> Self {
+        let (node_period, backtracks) = {
+            let observer = observer.lock().unwrap();
+            (observer.node_period(), observer.observes_backtracks())
+        };
+        Self { observer, node_period, backtracks }
+    }
+}
+
+impl SearchObserver for Forward<'_, '_> {
+    fn node_visited(&mut self, nodes: usize, state: &SearchFlow) {
+        if nodes.is_multiple_of(self.node_period) {
+            self.observer.lock().unwrap().node_visited(nodes, state);
+        }
+    }
+
+    fn depth_reached(&mut self, depth: usize) {
+        self.observer.lock().unwrap().depth_reached(depth);
+    }
+
+    fn backtrack(&mut self, depth: usize) {
+        if self.backtracks {
+            self.observer.lock().unwrap().backtrack(depth);
+        }
+    }
+
+    fn solution_found(&mut self, nodes: usize, state: &SearchFlow) {
+        self.observer.lock().unwrap().solution_found(nodes, state);
+    }
+}
+
+/// What one thread searched and found.
+#[derive(Default)]
+struct Worker {
+    nodes: usize,
+    table: Option<TableStats>,
+    pruned: PruneStats,
+    /// The solution and the index of the subtree it was found in.
+    found: Option<(usize, Solution)>,
+    interrupted: Option<SolveStatus>,
+}
+
+/// The limit or cancellation of `cfg` that stops a search after `nodes` nodes, if any.
+fn interrupt(cfg: &SolverConfig, deadline: Option<Instant>, nodes: usize) -> Option<SolveStatus> {
+    if cfg.max_nodes.is_some_and(|max| nodes >= max) {
+        return Some(SolveStatus::NodeLimit);
+    }
+    if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+        return Some(SolveStatus::TimedOut);
+    }
+    if cfg.cancel.as_ref().is_some_and(|cancel| cancel.is_cancelled()) {
+        return Some(SolveStatus::Cancelled);
+    }
+    return None;
+}
+
+/// Expands `state` level by level until there are at least `target` subtrees or none
+/// can be expanded further, and returns the subtrees in search order, or why the
+/// limits of `cfg` stopped the expansion. Nodes expanded here and their prune counts
+/// are added to `nodes` and `pruned`.
+fn split(
+    state: SearchFlow,
+    cfg: &SolverConfig,
+    deadline: Option<Instant>,
+    target: usize,
+    nodes: &mut usize,
+    pruned: &mut PruneStats,
+) -> Result<Vec<SearchFlow>, SolveStatus> {
+    let mut subtrees = vec![state];
+    while subtrees.len() < target && subtrees.iter().any(|state| !state.solved()) {
+        let mut next = Vec::new();
+        for mut state in subtrees {
+            if state.solved() {
+                next.push(state);
+                continue;
+            }
+            if let Some(status) = interrupt(cfg, deadline, *nodes) {
+                return Err(status);
+            }
+            *nodes += 1;
+            let feasible = state.feasible();
+            *pruned += state.take_prune_stats();
+            if !feasible {
+                continue;
+            }
+            for down in [false, true] {
+                for right in [false, true] {
+                    let mut child = state.clone();
+                    if child.extend(down, right) {
+                        next.push(child);
+                    }
+                }
+            }
+        }
+        subtrees = next;
+    }
+    return Ok(subtrees);
+}
+
+/// Splits the search tree near the root and lets `cfg.threads` threads take the
+/// subtrees one at a time, each with its own share of the transposition table. The
+/// first solution found stops the other threads.
+pub(crate) fn solve_board(board: &Board, cfg: &SolverConfig, observer: &mut dyn SearchObserver) -> SolveResult {
+    let start_time = Instant::now();
+    let deadline = cfg.time_limit.map(|limit| start_time + limit);
+
+    let (mut nodes, mut pruned) = (0, PruneStats::default());
+    let target = cfg.threads * SUBTREES_PER_THREAD;
+    let subtrees = match split(search_flow(board, cfg), cfg, deadline, target, &mut nodes, &mut pruned) {
+        Ok(subtrees) => subtrees,
+        Err(status) => {
+            return SolveResult {
+                status,
+                solution: None,
+                paths: None,
+                nodes,
+                elapsed: start_time.elapsed(),
+                table: None,
+                pruned,
+            };
+        }
+    };
+
+    let worker_cfg = SolverConfig { table_size_mb: (cfg.table_size_mb / cfg.threads).max(1), ..cfg.clone() };
+    let shared = Shared { nodes: AtomicUsize::new(nodes), solved: AtomicBool::new(false) };
+    let next = AtomicUsize::new(0);
+    // threads lock the observer to pass it events, so skip that if it observes nothing
+    let observer = observer.is_active().then(|| Mutex::new(observer));
+    let workers: Vec<Worker> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..cfg.threads).map(|_| scope.spawn(|| {
+            let mut forward = observer.as_ref().map(Forward::new);
+            let mut stop = |_: &SearchFlow| true;
+            let mut search = Search::new(&worker_cfg, &mut stop, &mut forward);
+            search.deadline = deadline;
+            search.shared = Some(&shared);
+
+            let mut worker = Worker::default();
+            while !shared.solved.load(Ordering::Relaxed) {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(subtree) = subtrees.get(i) else {
+                    break;
+                };
+                let mut state = subtree.clone();
+                let stopped = dfs_solve(&mut state, &mut search);
+                worker.pruned += state.prune_stats();
+                if stopped {
+                    if search.interrupted.is_none() {
+                        shared.solved.store(true, Ordering::Relaxed);
+                        worker.found = Some((i, to_input_orientation(state.solution(), cfg)));
+                    }
+                    break;
+                }
+            }
+            worker.nodes = search.nodes;
+            worker.table = search.table.map(|table| table.stats());
+            worker.interrupted = search.interrupted;
+            return worker;
+        })).collect();
+        handles.into_iter().map(|handle| handle.join().expect("solver thread panicked")).collect()
+    });
+
+    let mut table: Option<TableStats> = None;
+    for worker in &workers {
+        nodes += worker.nodes;
+        pruned += worker.pruned;
+        if let Some(stats) = worker.table {
+            *table.get_or_insert_with(TableStats::default) += stats;
+        }
+    }
+    let solution = workers.iter()
+        .filter_map(|worker| worker.found.as_ref())
+        .min_by_key(|(i, _)| *i)
+        .map(|(_, solution)| solution.clone());
+    let status = if solution.is_some() {
+        SolveStatus::Solved
+    } else {
+        workers.iter().find_map(|worker| worker.interrupted).unwrap_or(SolveStatus::Unsolvable)
+    };
+
+    SolveResult {
+        status,
+        paths: solution.as_ref().map(|solution| solution.paths()),
+        solution,
+        nodes,
+        elapsed: start_time.elapsed(),
+        table,
+        pruned,
+    }
+}
@@ -0,0 +1,84 @@
+use std::sync::Mutex;
+use std::thread;
+use std::time::Instant;
+
+use crate::{solve_board, Board, CancelToken, SolveResult, SolveStatus, SolverConfig};
+
+/// The outcome of racing several configurations on one board.
+#[derive(Debug, Clone)]
+pub struct PortfolioResult {
+    /// Index of the configuration that answered first, if any did before its limits.
+    pub winner: Option<usize>,
+    /// The winner's result, or the first configuration's if none answered, with the
+    /// time of the whole race as `elapsed`.
+    pub result: SolveResult,
+    /// Nodes searched by all configurations together.
+    pub nodes: usize,
+}
+
+/// Configurations to race: `cfg` in all four rotations, with `reflections` also each
+/// rotation of the mirrored board, which together cover all eight symmetries, and with
+/// `heuristics` each of those again with the connectivity and matching pruning toggled.
+pub fn portfolio_configs(cfg: &SolverConfig, reflections: bool, heuristics: bool) -> Vec<SolverConfig> {
+    let flips: &[bool] = if reflections { &[false, true] } else { &[false] };
+    let mut configs: Vec<SolverConfig> = flips.iter()
+        .flat_map(|&flip| (0..4).map(move |rotation| (flip, rotation)))
+        .map(|(flip_horizontal, rotation)| SolverConfig {
+            rotation,
+            flip_horizontal,
+            flip_vertical: false,
+            ..cfg.clone()
+        })
+        .collect();
+    if heuristics {
+        let toggled: Vec<SolverConfig> = configs.iter()
+            .map(|cfg| SolverConfig {
+                use_connectivity: !cfg.use_connectivity,
+                use_matching: !cfg.use_matching,
+                ..cfg.clone()
+            })
+            .collect();
+        configs.extend(toggled);
+    }
+    return configs;
+}
+
+/// Solves `board` with every configuration at once, each on its own thread, and
+/// returns the first to prove a solution or its absence; the others are cancelled.
+pub fn solve_portfolio(board: &Board, configs: &[SolverConfig]) -> PortfolioResult {
+    assert!(!configs.is_empty(), "a portfolio needs at least one configuration");
+    let start_time = Instant::now();
+
+    // each configuration gets a token of its own, still cancelled along with the caller's
+    let race: Vec<(SolverConfig, CancelToken)> = configs.iter()
+        .map(|cfg| {
+            let cancel = cfg.cancel.as_ref().map_or_else(CancelToken::new, CancelToken::child);
+            (SolverConfig { cancel: Some(cancel.clone()), ..cfg.clone() }, cancel)
+        })
+        .collect();
+    let winner: Mutex<Option<usize>> = Mutex::new(None);
+    let results: Vec<SolveResult> = thread::scope(|scope| {
+        let (race, winner) = (&race, &winner);
+        let handles: Vec<_> = (0..race.len()).map(|i| scope.spawn(move || {
+            let result = solve_board(board, &race[i].0, &mut ());
+            if matches!(result.status, SolveStatus::Solved | SolveStatus::Unsolvable) {
+                let mut winner = winner.lock().unwrap();
+                if winner.is_none() {
+                    *winner = Some(i);
+                    race.iter().enumerate()
+                        .filter(|&(j, _)| j != i)
+                        .for_each(|(_, (_, cancel))| cancel.cancel());
+                }
+            }
+            return result;
+        })).collect();
+        handles.into_iter().map(|handle| handle.join().expect("solver thread panicked")).collect()
+    });
+
+    let winner = winner.into_inner().unwrap();
+    let nodes = results.iter().map(|result| result.nodes).sum();
+    let index = winner.unwrap_or(0);
+    let mut result = results.into_iter().nth(index).expect("one result per configuration");
+    result.elapsed = start_time.elapsed();
+    return PortfolioResult { winner, result, nodes };
+}
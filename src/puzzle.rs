@@ -0,0 +1,130 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{Board, Difficulty, Format, ParseError, Solution, ValidationError};
+
+/// A board with optional metadata and a known solution. Text formats keep the
+/// metadata in `# key: value` comment lines, e.g. `# seed: 42`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Puzzle {
+    pub board: Board,
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub seed: Option<u64>,
+    pub difficulty: Option<Difficulty>,
+    pub solution: Option<Solution>,
+}
+
+/// The JSON layout of a [`Puzzle`].
+#[derive(Serialize, Deserialize)]
+struct PuzzleJson {
+    height: usize,
+    width: usize,
+    board: Board,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    author: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    difficulty: Option<Difficulty>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    solution: Option<Solution>,
+}
+
+impl Puzzle {
+    pub fn new(board: Board) -> Puzzle {
+        Puzzle { board, title: None, author: None, seed: None, difficulty: None, solution: None }
+    }
+
+    /// Reads a puzzle in the [`Format`] detected from `input`.
+    pub fn parse(input: &str) -> Result<Puzzle, ParseError> {
+        Puzzle::parse_as(input, Format::detect(input))
+    }
+
+    pub fn parse_as(input: &str, format: Format) -> Result<Puzzle, ParseError> {
+        if format == Format::Json {
+            return Puzzle::from_json(input);
+        }
+
+        let mut puzzle = Puzzle::new(Board::parse_as(input, format)?);
+        for line in input.lines() {
+            let Some((key, value)) = line.trim().strip_prefix('#').and_then(|comment| comment.split_once(':')) else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim() {
+                "title" => puzzle.title = Some(value.to_string()),
+                "author" => puzzle.author = Some(value.to_string()),
+                "seed" => puzzle.seed = value.parse().ok(),
+                "difficulty" => puzzle.difficulty = value.parse().ok(),
+                _ => {}
+            }
+        }
+        return Ok(puzzle);
+    }
+
+    fn from_json(input: &str) -> Result<Puzzle, ParseError> {
+        let json: PuzzleJson = serde_json::from_str(input).map_err(|err| ParseError::Json {
+            line: err.line(),
+            column: err.column(),
+            message: err.to_string(),
+        })?;
+
+        let (height, width) = (json.board.height(), json.board.width());
+        if (json.height, json.width) != (height, width) {
+            return Err(ValidationError::Dimensions { expected: (json.height, json.width), found: (height, width) }.into());
+        }
+        if let Some(solution) = &json.solution {
+            let edges = solution.edges();
+            let cells = height * width;
+            if (edges.h, edges.w) != (height, width)
+                || edges.down.len() != cells || edges.right.len() != cells || solution.colors().len() != cells {
+                return Err(ValidationError::SolutionSize.into());
+            }
+        }
+
+        return Ok(Puzzle {
+            board: json.board,
+            title: json.title,
+            author: json.author,
+            seed: json.seed,
+            difficulty: json.difficulty,
+            solution: json.solution,
+        });
+    }
+
+    /// Writes the puzzle in `format`, or returns `None` if the board cannot be written
+    /// as letters. Only JSON keeps the solution.
+    pub fn to_text(&self, format: Format) -> Option<String> {
+        if format == Format::Json {
+            let json = PuzzleJson {
+                height: self.board.height(),
+                width: self.board.width(),
+                board: self.board.clone(),
+                title: self.title.clone(),
+                author: self.author.clone(),
+                seed: self.seed,
+                difficulty: self.difficulty,
+                solution: self.solution.clone(),
+            };
+            return Some(serde_json::to_string(&json).expect("puzzles serialize") + "\n");
+        }
+
+        let mut result = String::new();
+        if let Some(title) = &self.title {
+            result.push_str(&format!("# title: {}\n", title));
+        }
+        if let Some(author) = &self.author {
+            result.push_str(&format!("# author: {}\n", author));
+        }
+        if let Some(seed) = self.seed {
+            result.push_str(&format!("# seed: {}\n", seed));
+        }
+        if let Some(difficulty) = self.difficulty {
+            result.push_str(&format!("# difficulty: {}\n", difficulty));
+        }
+        result.push_str(&self.board.to_text(format)?);
+        return Some(result);
+    }
+}
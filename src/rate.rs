@@ -0,0 +1,181 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::gen::path_lengths;
+use crate::search::SearchFlow;
+use crate::{search_flow, Board, Solution, SolverConfig};
+
+/// Searches larger than this are rated extreme without finishing.
+const NODE_LIMIT: usize = 20_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+    Extreme,
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Medium => "medium",
+            Difficulty::Hard => "hard",
+            Difficulty::Extreme => "extreme",
+        })
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Difficulty, String> {
+        match name {
+            "easy" => Ok(Difficulty::Easy),
+            "medium" => Ok(Difficulty::Medium),
+            "hard" => Ok(Difficulty::Hard),
+            "extreme" => Ok(Difficulty::Extreme),
+            _ => Err(format!("unknown difficulty `{}`", name)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rating {
+    pub score: f64,
+    pub difficulty: Difficulty,
+    pub solved: bool,
+    /// Nodes searched under the default solver configuration.
+    pub nodes: usize,
+    /// Most nested choices (cells with two or more legal moves) on any searched line.
+    pub max_branching_depth: usize,
+    /// Cells decided from the start by moves that were the only legal one, before the
+    /// search first had a choice.
+    pub forced_cells: usize,
+    pub cells: usize,
+    pub mean_path_len: f64,
+    pub max_path_len: usize,
+}
+
+#[derive(Default)]
+struct Walk {
+    nodes: usize,
+    solution: Option<Solution>,
+}
+
+/// Applies moves to `state` as long as exactly one is legal and returns how many were
+/// applied.
+fn propagate(state: &mut SearchFlow) -> usize {
+    let mut forced = 0;
+    while !state.solved() && state.feasible() {
+        let mut moves: Vec<(bool, bool)> = Vec::new();
+        for down in [false, true] {
+            for right in [false, true] {
+                if state.extend(down, right) {
+                    state.undo();
+                    moves.push((down, right));
+                }
+            }
+        }
+        let [(down, right)] = moves[..] else {
+            break;
+        };
+        state.extend(down, right);
+        forced += 1;
+    }
+    return forced;
+}
+
+/// Searches below `state` in the solver's order and returns whether the search ended,
+/// on a solution or the node limit, and the most nested choices on any searched line
+/// from `state` on. Leaves `state` as it found it. Once the search has ended, the
+/// remaining moves are only tried to count the legal ones.
+fn walk(state: &mut SearchFlow, stats: &mut Walk) -> (bool, usize) {
+    stats.nodes += 1;
+    if stats.nodes > NODE_LIMIT {
+        return (true, 0);
+    }
+
+    if state.solved() {
+        stats.solution = Some(state.solution());
+        return (true, 0);
+    }
+
+    if !state.feasible() {
+        return (false, 0);
+    }
+
+    let (mut moves, mut ended, mut branching_depth) = (0, false, 0);
+    for down in [false, true] {
+        for right in [false, true] {
+            if !state.extend(down, right) {
+                continue;
+            }
+            moves += 1;
+            if !ended {
+                let (child_ended, child_depth) = walk(state, stats);
+                ended = child_ended;
+                branching_depth = branching_depth.max(child_depth);
+            }
+            state.undo();
+        }
+    }
+
+    return (ended, branching_depth + (moves >= 2) as usize);
+}
+
+/// Rates how hard `board` is for a backtracking search. The score grows with the
+/// number of nodes per cell, the depth of nested choices, the share of cells that
+/// are not forced from the start, and the length of the paths.
+/// Boards without a solution, or that exhaust the node limit, are rated extreme.
+pub fn rate_board(board: &Board) -> Rating {
+    let cfg = SolverConfig::default();
+    let mut state = search_flow(board, &cfg);
+    let cells = state.len();
+
+    let forced_cells = propagate(&mut state.clone());
+    let mut stats = Walk::default();
+    let (_, max_branching_depth) = walk(&mut state, &mut stats);
+
+    let lengths = stats.solution.as_ref()
+        .map(path_lengths)
+        .unwrap_or_default();
+    let mean_path_len = if lengths.is_empty() {
+        0.0
+    } else {
+        lengths.iter().sum::<usize>() as f64 / lengths.len() as f64
+    };
+    let max_path_len = lengths.iter().copied().max().unwrap_or(0);
+
+    let solved = stats.solution.is_some();
+    let side = (cells as f64).sqrt();
+    let score = 25.0 * (stats.nodes as f64 / cells as f64).max(1.0).log10()
+        + 5.0 * max_branching_depth as f64 / side
+        + 10.0 * (1.0 - forced_cells as f64 / cells as f64)
+        + 5.0 * mean_path_len / side;
+    let difficulty = if !solved || score >= 90.0 {
+        Difficulty::Extreme
+    } else if score >= 60.0 {
+        Difficulty::Hard
+    } else if score >= 30.0 {
+        Difficulty::Medium
+    } else {
+        Difficulty::Easy
+    };
+
+    Rating {
+        score,
+        difficulty,
+        solved,
+        nodes: stats.nodes,
+        max_branching_depth,
+        forced_cells,
+        cells,
+        mean_path_len,
+        max_path_len,
+    }
+}
@@ -0,0 +1,349 @@
+use crate::{Board, Solution, SolutionEdges};
+
+/// The web app's palette; color `n` uses entry `(n - 1) % 16`.
+pub const FLOW_FREE_COLORS: [(u8, u8, u8); 16] = [
+    (0xe7, 0x4c, 0x3c),
+    (0x34, 0x98, 0xdb),
+    (0x2e, 0xcc, 0x71),
+    (0xf1, 0xc4, 0x0f),
+    (0xe6, 0x7e, 0x22),
+    (0x9b, 0x59, 0xb6),
+    (0x1a, 0xbc, 0x9c),
+    (0xe9, 0x1e, 0x63),
+    (0x00, 0xbc, 0xd4),
+    (0xcd, 0xdc, 0x39),
+    (0x3f, 0x51, 0xb5),
+    (0xff, 0xc1, 0x07),
+    (0x79, 0x55, 0x48),
+    (0x9e, 0x9e, 0x9e),
+    (0xff, 0x57, 0x22),
+    (0x60, 0x7d, 0x8b),
+];
+
+/// RGB of color `n`, or `None` for an empty cell.
+pub fn rgb(n: usize) -> Option<(u8, u8, u8)> {
+    if n == 0 {
+        return None;
+    }
+    return Some(FLOW_FREE_COLORS[(n - 1) % FLOW_FREE_COLORS.len()]);
+}
+
+const RESET: &str = "\x1b[0m";
+
+fn ansi_fg(n: usize) -> String {
+    match rgb(n) {
+        Some((r, g, b)) => format!("\x1b[38;2;{};{};{}m", r, g, b),
+        None => RESET.to_string(),
+    }
+}
+
+/// Box-drawing character for cell `u` of a solution, or `None` for an endpoint.
+fn pipe(edges: &SolutionEdges, u: usize) -> Option<char> {
+    let SolutionEdges { w, down, right, .. } = edges;
+    let up = u >= *w && down[u - w];
+    let left = !u.is_multiple_of(*w) && right[u - 1];
+    return match (up, down[u], left, right[u]) {
+        (true, true, false, false) => Some('│'),
+        (false, false, true, true) => Some('─'),
+        (false, true, false, true) => Some('┌'),
+        (false, true, true, false) => Some('┐'),
+        (true, false, false, true) => Some('└'),
+        (true, false, true, false) => Some('┘'),
+        (false, false, false, false) => Some('·'),
+        _ => None,
+    };
+}
+
+/// Renders a solved board with box-drawing characters, one line per row. Endpoints
+/// show their color number and path cells show the shape of the path through them.
+pub fn text(solution: &Solution) -> String {
+    let (edges, colors) = (solution.edges(), solution.colors());
+    let SolutionEdges { h, w, right, .. } = edges;
+    let width = colors.iter().max().map_or(1, |c| c.to_string().len()).max(2);
+
+    let mut result = String::new();
+    for r in 0..*h {
+        for c in 0..*w {
+            let u = r * w + c;
+            let fill = if right[u] { '─' } else { ' ' };
+
+            match pipe(edges, u) {
+                Some(ch) => {
+                    result.push(ch);
+                    result.extend(std::iter::repeat_n(fill, width - 1));
+                }
+                None => result.push_str(&format!("{:0width$}", colors[u], width = width)),
+            }
+            if c + 1 < *w {
+                result.push(fill);
+            }
+        }
+        result.push('\n');
+    }
+    return result;
+}
+
+/// Renders a solved board in true color: pipes in the color of their path and
+/// endpoints as filled dots.
+pub fn ansi(solution: &Solution) -> String {
+    let (edges, colors) = (solution.edges(), solution.colors());
+    let SolutionEdges { h, w, right, .. } = edges;
+
+    let mut result = String::new();
+    for r in 0..*h {
+        let mut current = None;
+        for c in 0..*w {
+            let u = r * w + c;
+            if current != Some(colors[u]) {
+                result.push_str(&ansi_fg(colors[u]));
+                current = Some(colors[u]);
+            }
+            result.push(pipe(edges, u).unwrap_or('●'));
+            if c + 1 < *w {
+                result.push(if right[u] { '─' } else { ' ' });
+            }
+        }
+        result.push_str(RESET);
+        result.push('\n');
+    }
+    return result;
+}
+
+/// Renders an unsolved board in true color: endpoints as filled dots and empty
+/// cells as faint dots.
+pub fn ansi_board(board: &Board) -> String {
+    let mut result = String::new();
+    for row in board.rows() {
+        let cells: Vec<String> = row.iter()
+            .map(|&n| if n == 0 { format!("{}·", RESET) } else { format!("{}●", ansi_fg(n)) })
+            .collect();
+        result.push_str(&cells.join(" "));
+        result.push_str(RESET);
+        result.push('\n');
+    }
+    return result;
+}
+
+const CELL: usize = 44;
+const GAP: usize = 4;
+
+fn svg_hex(n: usize) -> String {
+    let (r, g, b) = rgb(n).unwrap_or((0, 0, 0));
+    return format!("#{:02x}{:02x}{:02x}", r, g, b);
+}
+
+fn svg_center(r: usize, c: usize) -> (usize, usize) {
+    return (GAP + c * (CELL + GAP) + CELL / 2, GAP + r * (CELL + GAP) + CELL / 2);
+}
+
+/// Opens an SVG document with the grid of empty cells, laid out like the web app.
+fn svg_grid(h: usize, w: usize) -> String {
+    let (width, height) = (w * (CELL + GAP) + GAP, h * (CELL + GAP) + GAP);
+    let mut result = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n",
+        width, height, width, height,
+    );
+    result.push_str(&format!("<rect width=\"{}\" height=\"{}\" fill=\"#e5e7eb\"/>\n", width, height));
+    for r in 0..h {
+        for c in 0..w {
+            result.push_str(&format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#ffffff\"/>\n",
+                GAP + c * (CELL + GAP), GAP + r * (CELL + GAP), CELL, CELL,
+            ));
+        }
+    }
+    return result;
+}
+
+fn svg_endpoint(r: usize, c: usize, n: usize) -> String {
+    let (x, y) = svg_center(r, c);
+    return format!(
+        "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\"/>\n\
+         <text x=\"{}\" y=\"{}\" fill=\"#ffffff\" font-family=\"sans-serif\" font-size=\"14\" font-weight=\"bold\" \
+         text-anchor=\"middle\" dominant-baseline=\"central\">{}</text>\n",
+        x, y, CELL * 3 / 8, svg_hex(n), x, y, n,
+    );
+}
+
+/// Renders an unsolved board as an SVG document.
+pub fn svg_board(board: &Board) -> String {
+    let mut result = svg_grid(board.height(), board.width());
+    for (r, row) in board.rows().iter().enumerate() {
+        for (c, &n) in row.iter().enumerate() {
+            if n > 0 {
+                result.push_str(&svg_endpoint(r, c, n));
+            }
+        }
+    }
+    result.push_str("</svg>\n");
+    return result;
+}
+
+/// Renders a solved board as an SVG document: one polyline per path, drawn from one
+/// endpoint to the other, under the endpoint circles.
+pub fn svg(solution: &Solution) -> String {
+    let (edges, colors) = (solution.edges(), solution.colors());
+    let (h, w) = (edges.h, edges.w);
+
+    let mut result = svg_grid(h, w);
+    let mut visited = vec![false; h * w];
+    let mut endpoints = Vec::new();
+    for start in 0..h * w {
+        if edges.neighbors(start).len() != 1 {
+            continue;
+        }
+        endpoints.push(start);
+        if visited[start] {
+            continue;
+        }
+
+        let mut points = Vec::new();
+        let (mut prev, mut u) = (usize::MAX, start);
+        loop {
+            visited[u] = true;
+            let (x, y) = svg_center(u / w, u % w);
+            points.push(format!("{},{}", x, y));
+            match edges.neighbors(u).into_iter().find(|&v| v != prev) {
+                Some(v) => (prev, u) = (u, v),
+                None => break,
+            }
+        }
+        result.push_str(&format!(
+            "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"8\" \
+             stroke-linecap=\"round\" stroke-linejoin=\"round\"/>\n",
+            points.join(" "), svg_hex(colors[start]),
+        ));
+    }
+    for u in endpoints {
+        result.push_str(&svg_endpoint(u / w, u % w, colors[u]));
+    }
+    result.push_str("</svg>\n");
+    return result;
+}
+
+/// Layout and colors of [`png`] and [`png_board`] images.
+#[derive(Debug, Clone)]
+pub struct PngStyle {
+    /// Side of a cell in pixels.
+    pub cell: usize,
+    /// Width of the grid lines between cells in pixels.
+    pub gap: usize,
+    /// Color `n` uses entry `(n - 1) % palette.len()`.
+    pub palette: Vec<(u8, u8, u8)>,
+}
+
+impl Default for PngStyle {
+    fn default() -> Self {
+        PngStyle { cell: CELL, gap: GAP, palette: FLOW_FREE_COLORS.to_vec() }
+    }
+}
+
+impl PngStyle {
+    fn color(&self, n: usize) -> (u8, u8, u8) {
+        if n == 0 || self.palette.is_empty() {
+            return (0, 0, 0);
+        }
+        return self.palette[(n - 1) % self.palette.len()];
+    }
+
+    fn center(&self, r: usize, c: usize) -> (usize, usize) {
+        return (
+            self.gap + c * (self.cell + self.gap) + self.cell / 2,
+            self.gap + r * (self.cell + self.gap) + self.cell / 2,
+        );
+    }
+}
+
+/// RGB pixel buffer with the grid of empty cells already drawn.
+struct Canvas {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    fn grid(h: usize, w: usize, style: &PngStyle) -> Canvas {
+        let (width, height) = (w * (style.cell + style.gap) + style.gap, h * (style.cell + style.gap) + style.gap);
+        let mut canvas = Canvas { width, height, pixels: [0xe5, 0xe7, 0xeb].repeat(width * height) };
+        for r in 0..h {
+            for c in 0..w {
+                let (x, y) = (style.gap + c * (style.cell + style.gap), style.gap + r * (style.cell + style.gap));
+                canvas.rect(x, y, x + style.cell, y + style.cell, (0xff, 0xff, 0xff));
+            }
+        }
+        return canvas;
+    }
+
+    /// Fills the pixels in `[x0, x1) x [y0, y1)`.
+    fn rect(&mut self, x0: usize, y0: usize, x1: usize, y1: usize, (r, g, b): (u8, u8, u8)) {
+        for y in y0..y1.min(self.height) {
+            for x in x0..x1.min(self.width) {
+                let i = 3 * (y * self.width + x);
+                self.pixels[i..i + 3].copy_from_slice(&[r, g, b]);
+            }
+        }
+    }
+
+    fn circle(&mut self, cx: usize, cy: usize, radius: usize, color: (u8, u8, u8)) {
+        for dy in 0..=2 * radius {
+            for dx in 0..=2 * radius {
+                let (ox, oy) = (dx.abs_diff(radius), dy.abs_diff(radius));
+                if ox * ox + oy * oy <= radius * radius && cx + dx >= radius && cy + dy >= radius {
+                    let (x, y) = (cx + dx - radius, cy + dy - radius);
+                    self.rect(x, y, x + 1, y + 1, color);
+                }
+            }
+        }
+    }
+
+    fn encode(&self) -> Result<Vec<u8>, png::EncodingError> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&self.pixels)?;
+        return Ok(bytes);
+    }
+}
+
+/// Rasterizes an unsolved board to PNG bytes, drawing endpoints as filled circles.
+pub fn png_board(board: &Board, style: &PngStyle) -> Result<Vec<u8>, png::EncodingError> {
+    let mut canvas = Canvas::grid(board.height(), board.width(), style);
+    for (r, row) in board.rows().iter().enumerate() {
+        for (c, &n) in row.iter().enumerate() {
+            if n > 0 {
+                let (x, y) = style.center(r, c);
+                canvas.circle(x, y, style.cell * 3 / 8, style.color(n));
+            }
+        }
+    }
+    return canvas.encode();
+}
+
+/// Rasterizes a solved board to PNG bytes, with the same layout as [`svg`] but
+/// without the color numbers.
+pub fn png(solution: &Solution, style: &PngStyle) -> Result<Vec<u8>, png::EncodingError> {
+    let (edges, colors) = (solution.edges(), solution.colors());
+    let SolutionEdges { h, w, down, right } = edges;
+    let mut canvas = Canvas::grid(*h, *w, style);
+    let half = (style.cell * 2 / 11).max(1) / 2;
+
+    for u in 0..h * w {
+        let (x, y) = style.center(u / w, u % w);
+        let color = style.color(colors[u]);
+        canvas.circle(x, y, half, color);
+        if right[u] {
+            canvas.rect(x, y - half, x + style.cell + style.gap, y + half + 1, color);
+        }
+        if down[u] {
+            canvas.rect(x - half, y, x + half + 1, y + style.cell + style.gap, color);
+        }
+    }
+    for (u, &n) in colors.iter().enumerate() {
+        if pipe(edges, u).is_none() {
+            let (x, y) = style.center(u / w, u % w);
+            canvas.circle(x, y, style.cell * 3 / 8, style.color(n));
+        }
+    }
+    return canvas.encode();
+}
@@ -1,17 +1,57 @@
+use std::cmp::{max, min};
+use std::collections::HashMap;
+use std::ops::AddAssign;
+
+use serde::{Deserialize, Serialize};
+
 use crate::dsu::UnionFind;
+use crate::matching::BipartiteGraph;
+use crate::{Solution, SolutionEdges};
 use link::Link;
-use modnum::Modnum;
 
-mod link;
-mod modnum;
+pub use table::{ReplacementPolicy, TableStats, TranspositionTable};
 
-const HASH_BASE: Modnum = Modnum(10_007);
+mod link;
+mod table;
+mod zobrist;
 
 #[derive(Debug, Clone, Copy)]
 pub struct SearchConfig {
     pub allow_zigzag: bool,
+    /// Maintain `hash()`; only a transposition table reads it.
+    pub use_table: bool,
     pub use_vcut: bool,
     pub use_diagonals: bool,
+    pub use_connectivity: bool,
+    pub use_matching: bool,
+}
+
+/// Number of nodes rejected by each feasibility check.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct PruneStats {
+    pub vcut: usize,
+    pub connectivity: usize,
+    pub matching: usize,
+}
+
+impl AddAssign for PruneStats {
+    fn add_assign(&mut self, other: PruneStats) {
+        self.vcut += other.vcut;
+        self.connectivity += other.connectivity;
+        self.matching += other.matching;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Cell {
+    /// Processed, or already has all of its edges.
+    Blocked,
+    /// Empty and unreached.
+    Free,
+    /// Open end of an uncolored fragment; leads to the fragment's other end.
+    Bridge(usize),
+    /// Open end of an unfinished color.
+    Terminal,
 }
 
 #[derive(Debug, Clone)]
@@ -29,14 +69,19 @@ pub struct SearchFlow {
 
     diagonal_head_count: [Vec<usize>; 2],
 
-    // TODO: move this out of struct
-    hash_multiplier: Vec<Modnum>,
+    // both heads of every color, and the number of non-head cells at or below each cell
+    head_pairs: Vec<(usize, usize)>,
+    free_below: Vec<usize>,
+
+    hash: u64,
+    hash_history: Vec<u64>,
 
     config: SearchConfig,
+    pruned: PruneStats,
 }
 
 impl SearchFlow {
-    pub fn from_with_config(board: &Vec<Vec<usize>>, config: SearchConfig) -> Self {
+    pub fn from_with_config(board: &[Vec<usize>], config: SearchConfig) -> Self {
         let (h, w) = (board.len(), board[0].len());
 
         let heads: Vec<Option<usize>> = board.iter().flatten()
@@ -74,28 +119,50 @@ impl SearchFlow {
             }
         }
 
-        let mut hash_multiplier: Vec<Modnum> = vec![Modnum(0); 2 * h * w];
-        hash_multiplier[0] = Modnum(1);
-        for i in 1..(2 * h * w) {
-            hash_multiplier[i] = hash_multiplier[i - 1] * HASH_BASE;
+        let mut head_cells: HashMap<usize, Vec<usize>> = HashMap::new();
+        for (u, head) in heads.iter().enumerate() {
+            if let Some(color) = head {
+                head_cells.entry(*color).or_default().push(u);
+            }
+        }
+        let head_pairs = head_cells.into_values()
+            .filter(|cells| cells.len() == 2)
+            .map(|cells| (cells[0], cells[1]))
+            .collect();
+
+        let mut free_below = vec![0; h * w];
+        for u in (0..(h * w)).rev() {
+            free_below[u] = (heads[u].is_none() as usize) +
+                if u + w < h * w { free_below[u + w] } else { 0 };
         }
 
-        return Self {
+        let mut result = Self {
             h, w, heads, dsu,
             down: vec![false; h * w],
             right: vec![false; h * w],
             search_order,
             search_depth: 0,
             diagonal_head_count,
-            hash_multiplier,
+            head_pairs,
+            free_below,
+            hash: 0,
+            hash_history: Vec::new(),
             config,
+            pruned: PruneStats::default(),
         };
+        if config.use_table {
+            result.hash = (0..result.len())
+                .fold(0, |hash, u| hash ^ result.link_hash(result.dsu.get_data(u)));
+        }
+        return result;
     }
 
     pub fn depth(&self) -> usize { self.search_depth }
 
     pub fn len(&self) -> usize { self.h * self.w }
 
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+
     pub fn solved(&self) -> bool { self.depth() == self.len() }
 
     fn next_depth(&mut self) { self.search_depth += 1; }
@@ -106,7 +173,42 @@ impl SearchFlow {
 
     fn get_degree(&self, u: usize) -> usize {
         ((u >= self.w && self.down[u - self.w]) as usize) +
-        ((u % self.w > 0 && self.right[u - 1]) as usize)
+        ((!u.is_multiple_of(self.w) && self.right[u - 1]) as usize)
+    }
+
+    /// Hash of the state reachable from here: the pairing of open path ends, the edges
+    /// leading into the frontier, and the depth. Two states with equal (collision-free)
+    /// hashes have identical search subtrees. Only kept up to date with `use_table`.
+    pub fn hash(&self) -> u64 {
+        self.hash ^ zobrist::key(zobrist::DEPTH, self.depth(), 0)
+    }
+
+    fn link_hash(&self, link: Link) -> u64 {
+        match link {
+            Link::Colored(color, s) => zobrist::key(zobrist::COLORED, s, color),
+            Link::Uncolored(s, t) if s == t => zobrist::key(zobrist::UNCOLORED, s, t),
+            Link::Uncolored(s, t) => {
+                zobrist::key(zobrist::UNCOLORED, s, t) ^ zobrist::key(zobrist::UNCOLORED, t, s)
+            },
+            Link::Complete(_) => 0,
+        }
+    }
+
+    fn edge_hash(&self, u: usize) -> u64 {
+        (if self.down[u] { zobrist::key(zobrist::DOWN, u, 0) } else { 0 }) ^
+        (if self.right[u] { zobrist::key(zobrist::RIGHT, u, 0) } else { 0 })
+    }
+
+    fn unite(&mut self, u: usize, v: usize) -> bool {
+        if !self.config.use_table {
+            return self.dsu.unite(u, v);
+        }
+        let before = self.link_hash(self.dsu.get_data(u)) ^ self.link_hash(self.dsu.get_data(v));
+        if !self.dsu.unite(u, v) {
+            return false;
+        }
+        self.hash ^= before ^ self.link_hash(self.dsu.get_data(u));
+        return true;
     }
 
     pub fn extend(&mut self, down: bool, right: bool) -> bool {
@@ -115,51 +217,64 @@ impl SearchFlow {
         let u = self.search_order[self.depth()];
 
         let up = u >= self.w && self.down[u - self.w];
-        let left = u % self.w > 0 && self.right[u - 1];
+        let left = !u.is_multiple_of(self.w) && self.right[u - 1];
 
         let degree = (down as usize) + (right as usize) + (up as usize) + (left as usize) +
             (self.is_head(u) as usize);
 
         if degree != 2 ||
            down && u + self.w >= self.len() ||
-           right && (u + 1) % self.w == 0 {
+           right && (u + 1).is_multiple_of(self.w) {
             return false;
         }
 
         if !self.config.allow_zigzag {
-            if right && up && self.right[u - self.w] ||
-                right && up && self.down[u - self.w + 1] ||
+            if right && up && (self.right[u - self.w] || self.down[u - self.w + 1]) ||
                 right && u >= self.w && self.down[u - self.w + 1] && self.right[u - self.w] ||
                 down && left && self.down[u - 1] {
                 return false;
             }
 
-            if self.config.use_diagonals {
-                if down && left && self.diagonal_head_count[0][u / self.w + u % self.w] == 0 ||
-                    down && right && self.diagonal_head_count[1][u / self.w + self.w - u % self.w] == 0 {
-                    return false;
-                }
+            if self.config.use_diagonals && (
+                down && left && self.diagonal_head_count[0][u / self.w + u % self.w] == 0 ||
+                down && right && self.diagonal_head_count[1][u / self.w + self.w - u % self.w] == 0
+            ) {
+                return false;
             }
         }
 
+        let prev_hash = self.hash;
+
         if down {
-            if !self.dsu.unite(u, u + self.w) {
+            if !self.unite(u, u + self.w) {
                 return false;
             }
             self.down[u] = true;
         }
 
         if right {
-            if !self.dsu.unite(u, u + 1) {
+            if !self.unite(u, u + 1) {
                 if down {
                     self.dsu.undo();
                     self.down[u] = false;
+                    self.hash = prev_hash;
                 }
                 return false;
             }
             self.right[u] = true;
         }
 
+        // The edges of the last placed cell of each column are part of the state: they
+        // are the incoming edges of the frontier cells, and zigzag checks read them. The
+        // edges of the cell above are never read again once `u` is placed.
+        if self.config.use_table {
+            self.hash ^= self.edge_hash(u);
+            if u >= self.w {
+                self.hash ^= self.edge_hash(u - self.w);
+            }
+            self.hash_history.push(prev_hash);
+        }
+
         if self.heads[u].is_some() {
             self.diagonal_head_count[0][u / self.w + u % self.w] -= 1;
             self.diagonal_head_count[1][u / self.w + self.w - u % self.w] -= 1;
@@ -177,6 +292,10 @@ impl SearchFlow {
 
         let u = self.search_order[self.depth()];
 
+        if self.config.use_table {
+            self.hash = self.hash_history.pop().unwrap();
+        }
+
         if self.heads[u].is_some() {
             self.diagonal_head_count[0][u / self.w + u % self.w] += 1;
             self.diagonal_head_count[1][u / self.w + self.w - u % self.w] += 1;
@@ -193,104 +312,232 @@ impl SearchFlow {
         }
     }
 
-    pub fn get_state(&self) -> Vec<usize> {
-        (0..self.len()).map(|u| {
-            match self.dsu.get_data(u) {
-                Link::Colored(color, s) => {
-                    if u == s { color } else { 0 }
-                },
-                Link::Uncolored(s, t) => {
-                    if u == s {
-                        self.len() + t
-                    } else if u == t {
-                        self.len() + s
-                    } else {
-                        0
-                    }
-                },
-                Link::Complete(_) => 0,
+    /// First unprocessed row of every column. The processed cells of a diagonal order
+    /// form a staircase, so this is the only frontier cell of that column.
+    fn frontier_rows(&self) -> Vec<usize> {
+        if self.solved() {
+            return vec![self.h; self.w];
+        }
+
+        let u = self.search_order[self.depth()];
+        let (r0, d0) = (u / self.w, u / self.w + u % self.w);
+
+        (0..self.w).map(|c| {
+            match d0.checked_sub(c) {
+                Some(r) if r < r0 => min(self.h, r + 1),
+                Some(r) => min(self.h, r),
+                None => 0,
             }
         }).collect()
     }
 
+    /// Column of the open end of the path containing head `u`, or `None` if complete.
+    fn open_end(&self, u: usize) -> Option<usize> {
+        match self.dsu.get_data(u) {
+            Link::Colored(_, s) => Some(s % self.w),
+            _ => None,
+        }
+    }
+
+    /// Every unfinished color has to cross each column strictly between its two open
+    /// ends, either through a free cell of that column below the frontier or through
+    /// an uncolored fragment spanning it. Returns true if some column is overloaded.
     fn vcut(&self) -> bool {
-        // let u = self.search_depth;
-        // let mut colored_heads: HashMap<usize, usize> = HashMap::new();
-        // let mut uncolored_stack: Vec<(usize, usize)> = Vec::new();
-        // let mut load = vec![0; self.w];
-
-        // for c in 0..self.w {
-        //     let v = (u - u % self.w) + c + (if c < u % self.w { self.w } else { 0 });
-        //     if v >= self.len() {
-        //         continue;
-        //     }
-
-        //     let degree = ((v >= self.w && self.down[v - self.w]) as usize) +
-        //         ((v % self.w > 0 && self.right[v - 1]) as usize);
-            
-        //     if let Some(color) = self.heads[v] {
-        //         if degree == 0 {
-        //             if colored_heads.contains_key(&color) {
-        //                 load[colored_heads[&color] + 1] += 1;
-        //                 load[c] -= 1;
-        //             } else {
-        //                 colored_heads.insert(color, c);
-        //             }
-        //         }
-        //     } else {
-        //         if degree == 1 {
-        //             match self.dsu.get_color(v) {
-        //                 Some(color) => {
-        //                     if colored_heads.contains_key(&color) {
-        //                         load[colored_heads[&color] + 1] += 1;
-        //                         load[c] -= 1;
-        //                     } else {
-        //                         colored_heads.insert(color, c);
-        //                     }
-        //                 },
-        //                 None => {
-        //                     if !uncolored_stack.is_empty() &&
-        //                             self.dsu.same(v, uncolored_stack.last().unwrap().0) {
-        //                         load[uncolored_stack.pop().unwrap().1 + 1] -= 1;
-        //                         load[c] += 1;
-        //                     } else {
-        //                         uncolored_stack.push((v, c));
-        //                     }
-        //                 },
-        //             }
-        //         } else if degree == 0 {
-        //             load[c] -= 1;
-        //             if c + 1 < self.w {
-        //                 load[c + 1] += 1;
-        //             }
-        //         }
-        //     }
-        // }
-
-        // for c in 1..self.w {
-        //     load[c] += load[c - 1];
-        // }
-
-        // for c in 0..self.w {
-        //     let mut v = (u - u % self.w) + c + (if c < u % self.w { 2 * self.w } else { self.w });
-        //     while load[c] > 0 && v < self.len() {
-        //         if self.heads[v].is_none() {
-        //             load[c] -= 1;
-        //         }
-        //         v += self.w;
-        //     }
-        //     if load[c] > 0 {
-        //         return true;
-        //     }
-        // }
+        let rows = self.frontier_rows();
+        let mut load = vec![0_isize; self.w + 1];
+
+        for &(s, t) in self.head_pairs.iter() {
+            if let (Some(a), Some(b)) = (self.open_end(s), self.open_end(t)) {
+                let (a, b) = (min(a, b), max(a, b));
+                if a + 1 < b {
+                    load[a + 1] += 1;
+                    load[b] -= 1;
+                }
+            }
+        }
+
+        for c in 0..self.w {
+            if rows[c] == self.h {
+                continue;
+            }
+
+            let v = rows[c] * self.w + c;
+            let free = self.free_below[v] - (self.get_degree(v) > 0 && !self.is_head(v)) as usize;
+            load[c] -= free as isize;
+            load[c + 1] += free as isize;
+
+            if let Link::Uncolored(s, t) = self.dsu.get_data(v) {
+                if v == s && s != t {
+                    let (a, b) = (min(c, t % self.w), max(c, t % self.w));
+                    load[a] -= 1;
+                    load[b + 1] += 1;
+                }
+            }
+        }
+
+        for c in 0..self.w {
+            if c > 0 {
+                load[c] += load[c - 1];
+            }
+            if load[c] > 0 {
+                return true;
+            }
+        }
 
         return false;
     }
 
+    fn classify(&self, rows: &[usize], u: usize) -> Cell {
+        if u / self.w < rows[u % self.w] {
+            return Cell::Blocked;
+        }
+        match (self.is_head(u), self.get_degree(u), self.dsu.get_data(u)) {
+            (true, 0, _) => Cell::Terminal,
+            (false, 0, _) => Cell::Free,
+            (false, 1, Link::Colored(_, _)) => Cell::Terminal,
+            (false, 1, Link::Uncolored(s, t)) => Cell::Bridge(if u == s { t } else { s }),
+            _ => Cell::Blocked,
+        }
+    }
+
+    fn neighbors(&self, u: usize) -> impl Iterator<Item = usize> {
+        let (r, c, w, h) = (u / self.w, u % self.w, self.w, self.h);
+        [
+            (r > 0).then(|| u - w),
+            (c > 0).then(|| u - 1),
+            (c + 1 < w).then(|| u + 1),
+            (r + 1 < h).then(|| u + w),
+        ].into_iter().flatten()
+    }
+
+    /// Splits the cells that `passable` admits into connected regions, where the open
+    /// end of a fragment also connects to its other end. Returns the region of every
+    /// cell and the number of regions.
+    fn regions(&self, cells: &[Cell], passable: impl Fn(Cell) -> bool) -> (Vec<Option<usize>>, usize) {
+        let mut region: Vec<Option<usize>> = vec![None; self.len()];
+        let mut num_regions = 0;
+        let mut stack: Vec<usize> = Vec::new();
+        for u in 0..self.len() {
+            if region[u].is_some() || !passable(cells[u]) {
+                continue;
+            }
+            region[u] = Some(num_regions);
+            stack.push(u);
+            while let Some(v) = stack.pop() {
+                let bridge = match cells[v] { Cell::Bridge(t) => Some(t), _ => None };
+                for x in self.neighbors(v).chain(bridge) {
+                    if region[x].is_none() && passable(cells[x]) {
+                        region[x] = Some(num_regions);
+                        stack.push(x);
+                    }
+                }
+            }
+            num_regions += 1;
+        }
+        return (region, num_regions);
+    }
+
+    /// Flood fills the unprocessed cells that any color may still pass through. Every
+    /// unfinished color needs a region (or direct contact) joining its two open ends,
+    /// and every region must be reachable by both ends of some unfinished color, as
+    /// its cells can only be covered by such a path. Returns true if either fails.
+    fn disconnected(&self) -> bool {
+        let rows = self.frontier_rows();
+        let cells: Vec<Cell> = (0..self.len()).map(|u| self.classify(&rows, u)).collect();
+        let (region, num_regions) = self.regions(&cells, |cell| matches!(cell, Cell::Free | Cell::Bridge(_)));
+
+        let touching = |u: usize| -> Vec<usize> {
+            self.neighbors(u).filter_map(|v| region[v]).collect()
+        };
+
+        let mut covered = vec![false; num_regions];
+        for &(s, t) in self.head_pairs.iter() {
+            let (a, b) = match (self.dsu.get_data(s), self.dsu.get_data(t)) {
+                (Link::Colored(_, a), Link::Colored(_, b)) => (a, b),
+                _ => continue,
+            };
+            let (regions_a, regions_b) = (touching(a), touching(b));
+            let mut shared = regions_a.iter().filter(|x| regions_b.contains(x)).peekable();
+            if shared.peek().is_none() && !self.neighbors(a).any(|v| v == b) {
+                return true;
+            }
+            for &x in shared {
+                covered[x] = true;
+            }
+        }
+
+        return covered.iter().any(|&x| !x);
+    }
+
+    /// Every open end on the frontier continues into the unprocessed cells until it
+    /// meets another open end, so frontier ends need pairwise distinct partners: other
+    /// frontier ends or unreached heads, joined through a common region of free cells.
+    /// Colored ends only pair with their own color, and a fragment's end never with its
+    /// other end. Returns true if no matching covers every frontier end.
+    fn unmatched(&self) -> bool {
+        let rows = self.frontier_rows();
+        let cells: Vec<Cell> = (0..self.len()).map(|u| self.classify(&rows, u)).collect();
+        let (region, _) = self.regions(&cells, |cell| cell == Cell::Free);
+
+        let color = |u: usize| match (cells[u], self.dsu.get_data(u)) {
+            (Cell::Terminal, Link::Colored(color, _)) => Some(color),
+            _ => None,
+        };
+
+        // frontier ends go first, unreached heads after them
+        let mut ends: Vec<usize> = (0..self.len())
+            .filter(|&u| matches!(cells[u], Cell::Bridge(_) | Cell::Terminal) && self.get_degree(u) > 0)
+            .collect();
+        let num_frontier = ends.len();
+        ends.extend((0..self.len()).filter(|&u| cells[u] == Cell::Terminal && self.get_degree(u) == 0));
+
+        let touching: Vec<Vec<usize>> = ends.iter()
+            .map(|&u| self.neighbors(u).filter_map(|v| region[v]).collect())
+            .collect();
+
+        let mut graph = BipartiteGraph::new(num_frontier, ends.len());
+        for i in 0..num_frontier {
+            let u = ends[i];
+            for j in 0..ends.len() {
+                let v = ends[j];
+                let compatible = match (cells[u], cells[v]) {
+                    (Cell::Bridge(t), _) => t != v,
+                    (_, Cell::Bridge(_)) => true,
+                    _ => color(u) == color(v),
+                };
+                if i != j && compatible && (
+                    touching[i].iter().any(|x| touching[j].contains(x)) ||
+                    self.neighbors(u).any(|x| x == v)
+                ) {
+                    graph.add_edge(i, j);
+                }
+            }
+        }
+
+        return graph.kuhn_matching() < num_frontier;
+    }
+
     pub fn feasible(&mut self) -> bool {
-        return !self.config.use_vcut || !self.vcut();
+        if self.config.use_vcut && self.vcut() {
+            self.pruned.vcut += 1;
+            return false;
+        }
+        if self.config.use_connectivity && self.disconnected() {
+            self.pruned.connectivity += 1;
+            return false;
+        }
+        if self.config.use_matching && self.unmatched() {
+            self.pruned.matching += 1;
+            return false;
+        }
+        return true;
     }
 
+    pub fn prune_stats(&self) -> PruneStats { self.pruned }
+
+    /// Returns the prune counts so far and starts counting again from zero.
+    pub fn take_prune_stats(&mut self) -> PruneStats { std::mem::take(&mut self.pruned) }
+
     pub fn dump(&self) -> String {
         let mut result = String::new();
         let mut row = String::new();
@@ -314,8 +561,12 @@ impl SearchFlow {
         return result;
     }
 
-    pub fn edges(&self) -> (usize, usize, Vec<bool>, Vec<bool>) {
-        (self.h, self.w, self.down.clone(), self.right.clone())
+    pub fn edges(&self) -> SolutionEdges {
+        SolutionEdges { h: self.h, w: self.w, down: self.down.clone(), right: self.right.clone() }
+    }
+
+    pub fn solution(&self) -> Solution {
+        Solution::new(self.edges(), self.colors())
     }
 
     pub fn colors(&self) -> Vec<usize> {
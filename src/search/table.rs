@@ -0,0 +1,128 @@
+use std::ops::AddAssign;
+
+use serde::{Deserialize, Serialize};
+
+/// Decides which entry survives when two states hash to the same slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplacementPolicy {
+    /// Always overwrite the slot with the newest state.
+    #[default]
+    Always,
+    /// Keep whichever state took more nodes to refute.
+    PreferLarger,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct TableStats {
+    pub capacity: usize,
+    pub probes: usize,
+    pub hits: usize,
+    pub stores: usize,
+}
+
+impl AddAssign for TableStats {
+    fn add_assign(&mut self, other: TableStats) {
+        self.capacity += other.capacity;
+        self.probes += other.probes;
+        self.hits += other.hits;
+        self.stores += other.stores;
+    }
+}
+
+impl TableStats {
+    pub fn hit_rate(&self) -> f64 {
+        if self.probes == 0 { 0.0 } else { self.hits as f64 / self.probes as f64 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Entry {
+    key: u64,
+    work: usize,
+}
+
+/// Direct-mapped table of search states known to have no solution. It starts small
+/// and doubles whenever half of its slots are filled, up to its memory cap, so small
+/// searches do not pay for a large table. Key `0` marks an empty slot.
+#[derive(Debug, Clone)]
+pub struct TranspositionTable {
+    entries: Vec<Entry>,
+    mask: usize,
+    /// Slots the table may grow to.
+    max_capacity: usize,
+    /// Slots holding a state.
+    filled: usize,
+    policy: ReplacementPolicy,
+    stats: TableStats,
+}
+
+impl TranspositionTable {
+    /// Largest table `with_memory` allocates, in megabytes.
+    pub const MAX_MEGABYTES: usize = 16 * 1024;
+
+    /// Slots of a new table, unless its cap is smaller.
+    const INITIAL_CAPACITY: usize = 1 << 12;
+
+    /// A table of at most `megabytes`, clamped to `1..=MAX_MEGABYTES`.
+    pub fn with_memory(megabytes: usize, policy: ReplacementPolicy) -> Self {
+        let bytes = megabytes.clamp(1, Self::MAX_MEGABYTES) << 20;
+        let slots = (bytes / std::mem::size_of::<Entry>()).max(1);
+        let max_capacity = 1 << (usize::BITS - 1 - slots.leading_zeros());
+        let capacity = max_capacity.min(Self::INITIAL_CAPACITY);
+
+        return Self {
+            entries: vec![Entry::default(); capacity],
+            mask: capacity - 1,
+            max_capacity,
+            filled: 0,
+            policy,
+            stats: TableStats { capacity, ..TableStats::default() },
+        };
+    }
+
+    /// Doubles the table, moving every state to its slot in the larger table. A
+    /// state's new slot is its old one or that plus the old capacity, so no two
+    /// states collide.
+    fn grow(&mut self) {
+        let capacity = self.entries.len() * 2;
+        let mut entries = vec![Entry::default(); capacity];
+        for entry in self.entries.iter().filter(|entry| entry.key != 0) {
+            entries[entry.key as usize & (capacity - 1)] = *entry;
+        }
+        self.entries = entries;
+        self.mask = capacity - 1;
+        self.stats.capacity = capacity;
+    }
+
+    fn normalize(key: u64) -> u64 { if key == 0 { 1 } else { key } }
+
+    pub fn contains(&mut self, key: u64) -> bool {
+        let key = Self::normalize(key);
+        self.stats.probes += 1;
+        let hit = self.entries[key as usize & self.mask].key == key;
+        if hit {
+            self.stats.hits += 1;
+        }
+        return hit;
+    }
+
+    pub fn insert(&mut self, key: u64, work: usize) {
+        let key = Self::normalize(key);
+        let slot = &mut self.entries[key as usize & self.mask];
+        let replace = match self.policy {
+            ReplacementPolicy::Always => true,
+            ReplacementPolicy::PreferLarger => slot.key == 0 || slot.work <= work,
+        };
+        if replace {
+            self.filled += (slot.key == 0) as usize;
+            *slot = Entry { key, work };
+            self.stats.stores += 1;
+            if self.filled * 2 > self.entries.len() && self.entries.len() < self.max_capacity {
+                self.grow();
+            }
+        }
+    }
+
+    pub fn stats(&self) -> TableStats { self.stats }
+}
@@ -0,0 +1,20 @@
+// Zobrist keys for the search state. Instead of storing a random table for every
+// (cell, value) pair, which would be quadratic in the board size, keys are derived
+// on demand by running the triple through the splitmix64 finalizer.
+
+pub const COLORED: u64 = 1;
+pub const UNCOLORED: u64 = 2;
+pub const DOWN: u64 = 3;
+pub const RIGHT: u64 = 4;
+pub const DEPTH: u64 = 5;
+
+fn mix(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    return x ^ (x >> 31);
+}
+
+pub fn key(tag: u64, a: usize, b: usize) -> u64 {
+    mix(mix(mix(tag) ^ a as u64) ^ b as u64)
+}
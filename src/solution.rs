@@ -0,0 +1,158 @@
+use serde::{Deserialize, Serialize};
+
+use crate::Board;
+
+/// Edge sets of a solved board: `down[u]`/`right[u]` tell whether cell
+/// `u = row * w + col` connects to the cell below/right of it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SolutionEdges {
+    pub h: usize,
+    pub w: usize,
+    pub down: Vec<bool>,
+    pub right: Vec<bool>,
+}
+
+impl SolutionEdges {
+    /// Cells connected to cell `u`.
+    pub fn neighbors(&self, u: usize) -> Vec<usize> {
+        let w = self.w;
+        return [
+            (u >= w && self.down[u - w]).then(|| u - w),
+            (!u.is_multiple_of(w) && self.right[u - 1]).then(|| u - 1),
+            self.down[u].then(|| u + w),
+            self.right[u].then(|| u + 1),
+        ].into_iter().flatten().collect();
+    }
+
+    /// Cells of the path through `start`, walking away from `from` until the path ends.
+    fn trace(&self, mut from: usize, start: usize) -> Vec<usize> {
+        let mut path = vec![start];
+        let mut u = start;
+        while let Some(v) = self.neighbors(u).into_iter().find(|&v| v != from) {
+            (from, u) = (u, v);
+            path.push(u);
+        }
+        return path;
+    }
+}
+
+/// The cells of one color's path as `(row, col)`, in order from one endpoint to the
+/// other.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColorPath {
+    pub color: usize,
+    pub cells: Vec<(usize, usize)>,
+}
+
+/// A solved board: the color of every cell and the edges between them. Serializes as
+/// `{"edges": {...}, "colors": [...]}`, the shape the server returns.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Solution {
+    edges: SolutionEdges,
+    colors: Vec<usize>,
+}
+
+impl Solution {
+    pub fn new(edges: SolutionEdges, colors: Vec<usize>) -> Solution {
+        Solution { edges, colors }
+    }
+
+    /// Colors every cell of `edges` after the endpoint of `board` its path starts from.
+    pub fn from_edges(board: &Board, edges: SolutionEdges) -> Solution {
+        let mut colors = vec![0; edges.h * edges.w];
+        for (&color, ends) in board.endpoints() {
+            let (r, c) = ends[0];
+            for u in edges.trace(usize::MAX, r * edges.w + c) {
+                colors[u] = color;
+            }
+        }
+        return Solution { edges, colors };
+    }
+
+    pub fn height(&self) -> usize {
+        self.edges.h
+    }
+
+    pub fn width(&self) -> usize {
+        self.edges.w
+    }
+
+    pub fn edges(&self) -> &SolutionEdges {
+        &self.edges
+    }
+
+    /// Color of every cell, row by row.
+    pub fn colors(&self) -> &[usize] {
+        &self.colors
+    }
+
+    pub fn color(&self, row: usize, col: usize) -> usize {
+        self.colors[row * self.edges.w + col]
+    }
+
+    /// The path of each color in color order, each starting from the endpoint that
+    /// comes first in row-major order.
+    pub fn paths(&self) -> Vec<ColorPath> {
+        let w = self.edges.w;
+        let mut paths: Vec<ColorPath> = Vec::new();
+        for u in 0..self.colors.len() {
+            let color = self.colors[u];
+            if color == 0 || self.edges.neighbors(u).len() != 1 || paths.iter().any(|path| path.color == color) {
+                continue;
+            }
+            let cells = self.edges.trace(usize::MAX, u).into_iter().map(|v| (v / w, v % w)).collect();
+            paths.push(ColorPath { color, cells });
+        }
+        paths.sort_by_key(|path| path.color);
+        return paths;
+    }
+
+    /// Builds a `height x width` solution whose cell `(r, c)` is this solution's cell
+    /// `source(r, c)`, connected to the same neighbors.
+    fn remap(&self, height: usize, width: usize, source: impl Fn(usize, usize) -> (usize, usize)) -> Solution {
+        let index = |r: usize, c: usize| {
+            let (sr, sc) = source(r, c);
+            return sr * self.edges.w + sc;
+        };
+        let mut edges = SolutionEdges {
+            h: height,
+            w: width,
+            down: vec![false; height * width],
+            right: vec![false; height * width],
+        };
+        let mut colors = vec![0; height * width];
+        for r in 0..height {
+            for c in 0..width {
+                let (u, neighbors) = (r * width + c, self.edges.neighbors(index(r, c)));
+                colors[u] = self.colors[index(r, c)];
+                edges.down[u] = r + 1 < height && neighbors.contains(&index(r + 1, c));
+                edges.right[u] = c + 1 < width && neighbors.contains(&index(r, c + 1));
+            }
+        }
+        return Solution { edges, colors };
+    }
+
+    /// The solution turned a quarter counter-clockwise, matching [`Board::rotate_ccw`].
+    pub fn rotate_ccw(&self) -> Solution {
+        let (h, w) = (self.height(), self.width());
+        return self.remap(w, h, |r, c| (c, w - 1 - r));
+    }
+
+    /// The solution turned a quarter clockwise, matching [`Board::rotate_cw`].
+    pub fn rotate_cw(&self) -> Solution {
+        let (h, w) = (self.height(), self.width());
+        return self.remap(w, h, |r, c| (h - 1 - c, r));
+    }
+
+    /// The solution mirrored left to right.
+    pub fn flip_horizontal(&self) -> Solution {
+        let (h, w) = (self.height(), self.width());
+        return self.remap(h, w, |r, c| (r, w - 1 - c));
+    }
+
+    /// The solution mirrored top to bottom.
+    pub fn flip_vertical(&self) -> Solution {
+        let (h, w) = (self.height(), self.width());
+        return self.remap(h, w, |r, c| (h - 1 - r, c));
+    }
+}
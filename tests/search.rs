@@ -0,0 +1,178 @@
+#![allow(clippy::needless_return)]
+
+use flow_solver::gen::{GenConfig, Generator};
+use flow_solver::{count_solutions, solve_board, Board, Solution, SolverConfig};
+use rand::{rngs::StdRng, SeedableRng};
+
+/// Solutions counted per board before giving up; the small boards stay well below it.
+const COUNT_LIMIT: usize = 100_000;
+
+/// Generated boards small enough to count all of their solutions, including boards
+/// that once exposed pruning bugs.
+fn small_boards() -> Vec<Board> {
+    let sizes = [(5, 5), (5, 6), (5, 7), (6, 6)];
+    let seeds = sizes.iter().flat_map(|&size| (0..8).map(move |seed| (size, seed)))
+        .chain([((5, 7), 338), ((5, 6), 139), ((6, 6), 8)]);
+    return seeds
+        .filter_map(|((height, width), seed)| {
+            let cfg = GenConfig { height, width, ..GenConfig::default() };
+            Generator::new(cfg, StdRng::seed_from_u64(seed)).unwrap().generate()
+        })
+        .map(|generated| generated.board)
+        .collect();
+}
+
+fn count(board: &Board, cfg: &SolverConfig) -> usize {
+    let result = count_solutions(board, cfg, Some(COUNT_LIMIT), &mut ());
+    assert!(result.exhaustive, "too many solutions to count:\n{}", board);
+    return result.count;
+}
+
+/// Asserts that `with` counts as many solutions as the default configuration on every
+/// small board, with and without zigzags.
+fn assert_same_counts(with: impl Fn(SolverConfig) -> SolverConfig) {
+    for board in small_boards() {
+        for allow_zigzag in [false, true] {
+            let cfg = SolverConfig { allow_zigzag, ..SolverConfig::default() };
+            assert_eq!(
+                count(&board, &with(cfg.clone())), count(&board, &cfg),
+                "allow_zigzag: {}, board:\n{}", allow_zigzag, board,
+            );
+        }
+    }
+}
+
+#[test]
+fn table_keeps_solution_counts() {
+    assert_same_counts(|cfg| SolverConfig { use_table: true, table_size_mb: 1, ..cfg });
+}
+
+/// Every puzzle bundled in `puzzles/`.
+fn bundled_puzzles() -> Vec<(String, Board)> {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/puzzles");
+    let mut puzzles: Vec<(String, Board)> = std::fs::read_dir(dir).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .map(|path| {
+            let input = std::fs::read_to_string(&path).unwrap();
+            (path.file_name().unwrap().to_string_lossy().into_owned(), Board::parse(&input).unwrap())
+        })
+        .collect();
+    puzzles.sort_by(|a, b| a.0.cmp(&b.0));
+    return puzzles;
+}
+
+/// Asserts that `with` solves exactly the bundled puzzles the default configuration
+/// solves.
+fn assert_same_solved(with: impl Fn(SolverConfig) -> SolverConfig) {
+    for (name, board) in bundled_puzzles() {
+        let cfg = SolverConfig::default();
+        let solved = solve_board(&board, &cfg, &mut ()).is_solved();
+        assert_eq!(solve_board(&board, &with(cfg), &mut ()).is_solved(), solved, "{}", name);
+    }
+}
+
+#[test]
+fn vcut_solves_bundled_puzzles() {
+    assert_same_solved(|cfg| SolverConfig { use_vcut: true, ..cfg });
+}
+
+/// Asserts that `solution` connects the endpoints of every color of `board` by paths
+/// that stay on the board and together cover every cell.
+fn assert_satisfies(board: &Board, solution: &Solution, name: &str) {
+    let (h, w) = (board.height(), board.width());
+    assert_eq!((solution.height(), solution.width()), (h, w), "{}", name);
+    let edges = solution.edges();
+    let leaves_board = |u: usize| edges.down[u] && u / w == h - 1 || edges.right[u] && u % w == w - 1;
+    assert!(!(0..h * w).any(leaves_board), "{}", name);
+
+    let paths = solution.paths();
+    assert_eq!(paths.len(), board.num_colors(), "{}", name);
+    for path in &paths {
+        let [a, b] = board.endpoints()[&path.color];
+        let ends = (path.cells[0], path.cells[path.cells.len() - 1]);
+        assert!(ends == (a, b) || ends == (b, a), "{}: color {}", name, path.color);
+    }
+    assert_eq!(paths.iter().map(|path| path.cells.len()).sum::<usize>(), h * w, "{}", name);
+}
+
+/// Every rotation of the board as given, mirrored left to right and mirrored top to
+/// bottom, which covers all eight symmetries and both flips.
+fn transforms() -> Vec<SolverConfig> {
+    return [(false, false), (true, false), (false, true)].into_iter()
+        .flat_map(|(flip_horizontal, flip_vertical)| (0..4).map(move |rotation| SolverConfig {
+            rotation,
+            flip_horizontal,
+            flip_vertical,
+            ..SolverConfig::default()
+        }))
+        .collect();
+}
+
+/// Every transform must return a solution of the board as given, and the same one as
+/// the untransformed search where the solution is unique.
+#[test]
+fn transforms_return_solutions_in_input_coordinates() {
+    for (name, board) in bundled_puzzles() {
+        let cfg = SolverConfig::default();
+        let expected = solve_board(&board, &cfg, &mut ()).solution;
+        let unique = count_solutions(&board, &cfg, Some(2), &mut ()).count <= 1;
+        for cfg in transforms() {
+            let result = solve_board(&board, &cfg, &mut ());
+            let name = format!(
+                "{} (rotation {}, flip_horizontal {}, flip_vertical {})",
+                name, cfg.rotation, cfg.flip_horizontal, cfg.flip_vertical,
+            );
+            if let Some(solution) = &result.solution {
+                assert_satisfies(&board, solution, &name);
+            }
+            assert_eq!(result.solution.is_some(), expected.is_some(), "{}", name);
+            if unique {
+                assert_eq!(result.solution, expected, "{}", name);
+            }
+        }
+    }
+}
+
+#[test]
+fn vcut_keeps_solution_counts() {
+    assert_same_counts(|cfg| SolverConfig { use_vcut: true, ..cfg });
+}
+
+#[test]
+fn connectivity_keeps_solution_counts() {
+    assert_same_counts(|cfg| SolverConfig { use_connectivity: true, ..cfg });
+}
+
+#[test]
+fn matching_keeps_solution_counts() {
+    assert_same_counts(|cfg| SolverConfig { use_matching: true, ..cfg });
+}
+
+#[test]
+fn unique_boards_have_one_solution() {
+    let boards = (0..4).map(|seed| (7, 7, false, seed)).chain([(6, 6, true, 21)]);
+    for (height, width, allow_zigzag, seed) in boards {
+        let cfg = GenConfig { height, width, allow_zigzag, unique: Some(32), ..GenConfig::default() };
+        let generated = Generator::new(cfg, StdRng::seed_from_u64(seed)).unwrap().generate().unwrap();
+        let cfg = SolverConfig { allow_zigzag: true, ..SolverConfig::default() };
+        assert_eq!(count(&generated.board, &cfg), 1, "seed: {}, board:\n{}", seed, generated.board);
+    }
+}
+
+/// Counting without a node limit once took minutes on this board.
+#[test]
+fn unique_generation_finishes_on_a_mid_size_board() {
+    let cfg = GenConfig { height: 12, width: 12, unique: Some(32), ..GenConfig::default() };
+    assert!(Generator::new(cfg, StdRng::seed_from_u64(3)).unwrap().generate().is_some());
+}
+
+#[test]
+fn same_seed_generates_same_board() {
+    for unique in [None, Some(32)] {
+        let cfg = GenConfig { height: 8, width: 8, unique, ..GenConfig::default() };
+        let generate = || Generator::new(cfg.clone(), StdRng::seed_from_u64(7)).unwrap().generate().unwrap();
+        let (first, second) = (generate(), generate());
+        assert_eq!(first.board, second.board);
+        assert_eq!(first.solution, second.solution);
+    }
+}
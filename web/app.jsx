@@ -55,6 +55,8 @@ function App() {
   const [useVcut, setUseVcut] = useState(false);
   const [useTable, setUseTable] = useState(false);
   const [useDiagonals, setUseDiagonals] = useState(true);
+  const [useConnectivity, setUseConnectivity] = useState(false);
+  const [useMatching, setUseMatching] = useState(false);
 
   // Status + solution
   const [status, setStatus] = useState('');
@@ -124,6 +126,8 @@ function App() {
         use_vcut: !!useVcut,
         use_table: !!useTable,
         use_diagonals: !!useDiagonals,
+        use_connectivity: !!useConnectivity,
+        use_matching: !!useMatching,
       };
       const res = await fetch('/solve', {
         method: 'POST',
@@ -242,6 +246,8 @@ function App() {
               <label className="inline-flex items-center gap-2 text-sm text-neutral-800"><input type="checkbox" className="accent-neutral-600" checked={useVcut} onChange={(e) => setUseVcut(e.target.checked)} /> VCut Pruning </label>
               <label className="inline-flex items-center gap-2 text-sm text-neutral-800"><input type="checkbox" className="accent-neutral-600" checked={useTable} onChange={(e) => setUseTable(e.target.checked)} /> Use Cache (beta) </label>
               <label className="inline-flex items-center gap-2 text-sm text-neutral-800"><input type="checkbox" className="accent-neutral-600" checked={useDiagonals} onChange={(e) => setUseDiagonals(e.target.checked)} /> Diagonal Pruning </label>
+              <label className="inline-flex items-center gap-2 text-sm text-neutral-800"><input type="checkbox" className="accent-neutral-600" checked={useConnectivity} onChange={(e) => setUseConnectivity(e.target.checked)} /> Connectivity Pruning </label>
+              <label className="inline-flex items-center gap-2 text-sm text-neutral-800"><input type="checkbox" className="accent-neutral-600" checked={useMatching} onChange={(e) => setUseMatching(e.target.checked)} /> Matching Pruning </label>
             </div>
             <div className="flex items-center gap-3 pt-2">
               <button onClick={handleSolve} className="inline-flex items-center justify-center rounded border border-neutral-300 bg-neutral-100 px-3 py-2 text-sm text-black hover:bg-white">Solve</button>